@@ -1,16 +1,64 @@
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+#[path = "build/blocking.rs"]
+mod blocking;
+#[path = "build/diagnostics.rs"]
+mod diagnostics;
+#[path = "build/downgrade.rs"]
+mod downgrade;
+#[path = "build/enums.rs"]
+mod enums;
+#[path = "build/examples.rs"]
+mod examples;
+#[path = "build/filter.rs"]
+mod filter;
+#[path = "build/hoist.rs"]
+mod hoist;
+#[path = "build/lenient.rs"]
+mod lenient;
+#[path = "build/media.rs"]
+mod media;
+#[path = "build/mock.rs"]
+mod mock;
+#[path = "build/modules.rs"]
+mod modules;
+#[path = "build/naming.rs"]
+mod naming;
+#[path = "build/overlay.rs"]
+mod overlay;
+#[path = "build/pagination.rs"]
+mod pagination;
+#[path = "build/simplify.rs"]
+mod simplify;
+#[path = "build/transport.rs"]
+mod transport;
+#[path = "build/unions.rs"]
+mod unions;
+#[path = "build/visit.rs"]
+mod visit;
+
+use diagnostics::Diagnostics;
+use simplify::{Simplifier, simplify_schema};
+
+/// Upstream location of the Cloudflare OpenAPI schema.
+const DEFAULT_SCHEMA_URL: &str = "https://developers.cloudflare.com/api/openapi.json";
+
+/// Vendored schema snapshot, relative to the crate root.
+const SNAPSHOT_PATH: &str = "schema/openapi.json.gz";
+
+/// SHA-256 of the decompressed snapshot, in `sha256sum` format.
+const SNAPSHOT_CHECKSUM_PATH: &str = "schema/openapi.json.sha256";
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let out_dir = PathBuf::from(env::var("OUT_DIR")?);
 
-    // Download the OpenAPI schema
-    let schema_url = "https://developers.cloudflare.com/api/openapi.json";
-    println!("cargo:warning=Downloading OpenAPI schema from {}", schema_url);
-
-    let schema_content = reqwest::blocking::get(schema_url)?
-        .text()?;
+    // Load the OpenAPI schema (vendored snapshot unless explicitly overridden)
+    let schema_content = load_schema()?;
 
     let schema_path = out_dir.join("openapi.json");
     fs::write(&schema_path, &schema_content)?;
@@ -22,47 +70,96 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             e
         })?;
 
-    // Add missing operation IDs
-    if let Some(paths) = spec_value.get_mut("paths").and_then(|p| p.as_object_mut()) {
-        for (path_name, path_item) in paths.iter_mut() {
-            if let Some(operations) = path_item.as_object_mut() {
-                for (method, operation) in operations.iter_mut() {
-                    // Skip non-operation fields like parameters, summary, etc.
-                    if !["get", "put", "post", "delete", "options", "head", "patch", "trace"].contains(&method.as_str()) {
-                        continue;
-                    }
-
-                    if let Some(op_obj) = operation.as_object_mut() {
-                        // Only add operationId if it's missing
-                        if !op_obj.contains_key("operationId") {
-                            // Generate operation ID from method and path
-                            let operation_id = format!("{}_{}",
-                                method,
-                                path_name
-                                    .trim_start_matches('/')
-                                    .replace('/', "_")
-                                    .replace('{', "")
-                                    .replace('}', "")
-                                    .replace('-', "_")
-                            );
-                            op_obj.insert(
-                                "operationId".to_string(),
-                                serde_json::Value::String(operation_id)
-                            );
-                        }
-                    }
-                }
-            }
-        }
+    // Every change made to the schema is recorded in the diagnostics report
+    let mut diagnostics = Diagnostics::default();
+
+    // Apply targeted fixes from overlays/ before anything else touches the spec
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR")?);
+    overlay::apply_overlays(
+        &mut spec_value,
+        &manifest_dir.join(overlay::OVERLAY_DIR),
+        &mut diagnostics,
+    )?;
+
+    // Rewrite OpenAPI 3.1 constructs (type arrays, `const`, `$ref` siblings)
+    // into the OpenAPI 3.0 forms that openapiv3 and the passes below expect
+    downgrade::downgrade_to_3_0(&mut spec_value, &mut diagnostics);
+
+    // Give every operation a readable method name. This runs on the whole API
+    // so names do not depend on the enabled product areas.
+    println!("cargo:rerun-if-env-changed=CLOUDFLARE_API_UPDATE_OPERATION_NAMES");
+    let update_names = env::var_os("CLOUDFLARE_API_UPDATE_OPERATION_NAMES").is_some();
+    let operation_names = naming::assign_operation_names(
+        &mut spec_value,
+        &manifest_dir.join(naming::NAMES_PATH),
+        update_names,
+        &mut diagnostics,
+    )?;
+    record_operation_names(&operation_names, &manifest_dir, update_names)?;
+
+    // Keep only the product areas enabled as cargo features
+    filter::filter_product_areas(
+        &mut spec_value,
+        &filter::enabled_features(),
+        &mut diagnostics,
+    )?;
+
+    // Send every request body with one media type Progenitor supports
+    media::supported_request_bodies(&mut spec_value, &mut diagnostics);
+
+    // Simplify the shapes Progenitor cannot handle (allOf, unions, constrained
+    // enums) wherever a schema appears: components, parameters, headers, and
+    // request and response bodies
+    let mut simplifier = Simplifier::new(&spec_value, &mut diagnostics);
+    visit::for_each_schema(&mut spec_value, &mut |schema, location| {
+        simplify_schema(schema, location, &mut simplifier)
+    });
+
+    // Give inline objects and enums stable names of their own, shared by
+    // every identical copy
+    hoist::hoist_inline_schemas(&mut spec_value);
+
+    // Forgiving types: required fields nullable, undeclared fields kept in `extra`
+    let lenient = env::var_os("CARGO_FEATURE_LENIENT").is_some();
+    if lenient {
+        lenient::relax_required(&mut spec_value);
     }
 
-    // Simplify schemas that use allOf with just one item (Progenitor doesn't handle this well)
-    if let Some(components) = spec_value.get_mut("components") {
-        if let Some(schemas) = components.get_mut("schemas").and_then(|s| s.as_object_mut()) {
-            for schema in schemas.values_mut() {
-                simplify_schema(schema);
-            }
-        }
+    // Split the API into per-product modules; shared schemas go to `common`
+    let module_tree = modules::ModuleTree::plan(&spec_value);
+
+    // Spec examples for the round-trip tests in tests/examples.rs
+    let known_bad = examples::read_known_bad(&manifest_dir.join(examples::KNOWN_BAD_PATH))?;
+    fs::write(
+        out_dir.join("examples.rs"),
+        examples::example_table(&spec_value, &module_tree, &known_bad, &mut diagnostics),
+    )?;
+
+    // Report every change made to the schema, before anything can fail on it
+    let schema_version = spec_value
+        .pointer("/info/version")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown")
+        .to_string();
+    let (diagnostics_path, _) =
+        diagnostics.write(&out_dir, &schema_version, &sha256_hex(&schema_content))?;
+    let summary = diagnostics.summary();
+    if !summary.is_empty() {
+        println!(
+            "cargo:warning=Schema patched ({}), see {:?}",
+            summary, diagnostics_path
+        );
+    }
+
+    // Record which list operations are paginated
+    fs::write(
+        out_dir.join("pagination.rs"),
+        pagination::pagination_table(&spec_value),
+    )?;
+
+    // Canned responses for the mock server
+    if env::var_os("CARGO_FEATURE_MOCK").is_some() {
+        fs::write(out_dir.join("mock_routes.rs"), mock::mock_routes(&spec_value))?;
     }
 
     // Save the patched schema for debugging
@@ -75,17 +172,76 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             eprintln!("Failed to deserialize OpenAPI spec: {}", e);
             eprintln!("This is likely due to schema incompatibilities.");
             eprintln!("Patched schema saved at: {:?}", patched_schema_path);
+            eprintln!("Patch report saved at: {:?}", diagnostics_path);
             e
         })?;
 
-    // Generate the client code with patched schema
-    let mut generator = progenitor::Generator::default();
+    // String enums get an `Unknown(String)` variant unless marked exhaustive
+    let exhaustive_enums = enums::exhaustive_enums(&spec_value);
+
+    for module in module_tree.type_modules() {
+        let types_spec: openapiv3::OpenAPI =
+            serde_json::from_value(module_tree.types_spec(&spec_value, module))?;
+        let mut settings = progenitor::GenerationSettings::new();
+        for (type_name, path) in module_tree.replacements(Some(module)) {
+            settings.with_replacement(type_name, path, std::iter::empty());
+        }
+
+        let tokens = progenitor::Generator::new(&settings)
+            .generate_tokens(&types_spec)
+            .inspect_err(|e| eprintln!("Failed to generate types for {}: {}", module, e))?;
+
+        let types_file = out_dir.join(modules::ModuleTree::types_file(module));
+        if let Some(parent) = types_file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut tokens = enums::open_enums(tokens, &exhaustive_enums)?;
+        if lenient {
+            tokens = lenient::capture_unknown_fields(tokens)?;
+        }
+        fs::write(&types_file, modules::extract_types(tokens)?)?;
+    }
+
+    fs::write(out_dir.join("modules.rs"), module_tree.render())?;
+
+    // Generate the client code with patched schema. The client carries
+    // `ClientState` and reports every request to the hooks in `src/hooks.rs`,
+    // which also send it (see `build/transport.rs`).
+    // Operations are grouped into one extension trait per tag, and component
+    // schemas refer to the module types generated above.
+    let mut settings = progenitor::GenerationSettings::new();
+    settings
+        .with_interface(progenitor::InterfaceStyle::Builder)
+        .with_tag(progenitor::TagStyle::Separate)
+        .with_inner_type(quote::quote!(crate::ClientState))
+        .with_pre_hook_async(quote::quote!(crate::hooks::pre_request))
+        .with_post_hook(quote::quote!(crate::hooks::post_response));
+    for (type_name, path) in module_tree.replacements(None) {
+        settings.with_replacement(type_name, path, std::iter::empty());
+    }
+    let mut generator = progenitor::Generator::new(&settings);
 
     let tokens = generator.generate_tokens(&spec)
         .map_err(|e| {
             eprintln!("Failed to generate code with Progenitor: {}", e);
+            eprintln!("Patch report saved at: {:?}", diagnostics_path);
             e
         })?;
+    let tokens = operation_names.add_doc_aliases(tokens)?;
+    let tokens = transport::route_through_backend(tokens)?;
+    let mut tokens = enums::open_enums(tokens, &exhaustive_enums)?;
+    if lenient {
+        tokens = lenient::capture_unknown_fields(tokens)?;
+    }
+
+    // Synchronous mirror of the client
+    if env::var_os("CARGO_FEATURE_BLOCKING").is_some() {
+        fs::write(
+            out_dir.join("blocking.rs"),
+            blocking::blocking_client(&tokens)?,
+        )?;
+    }
+
     let generated_code = tokens.to_string();
 
     let output_file = out_dir.join("cloudflare_api.rs");
@@ -96,124 +252,192 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn simplify_schema(schema: &mut serde_json::Value) {
-    // Fix invalid schema combinations - enum with string constraints
-    if schema.get("enum").is_some() {
-        if let Some(obj) = schema.as_object_mut() {
-            // Remove string-specific constraints that don't make sense with enum
-            obj.remove("maxLength");
-            obj.remove("minLength");
-            obj.remove("pattern");
-            obj.remove("format");
-            // Ensure type is set to string for enums (unless it's explicitly something else)
-            if !obj.contains_key("type") {
-                obj.insert("type".to_string(), serde_json::json!("string"));
-            }
+/// Report operations missing from the checked-in names, and rewrite the file
+/// when `update` (`CLOUDFLARE_API_UPDATE_OPERATION_NAMES`) is set.
+///
+/// Rewriting fails if it would drop an entry: the operation was removed or
+/// moved upstream, and its method would disappear or be renamed. Such
+/// entries are removed (or re-keyed) by hand.
+fn record_operation_names(
+    names: &naming::OperationNames,
+    manifest_dir: &Path,
+    update: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = manifest_dir.join(naming::NAMES_PATH);
+    let removed: Vec<String> = names
+        .removed
+        .iter()
+        .map(|(key, name)| format!("`{}` ({})", name, key))
+        .collect();
+
+    if update {
+        if !removed.is_empty() {
+            return Err(format!(
+                "{}: {} recorded operations are no longer in the spec: {}; \
+                 remove or re-key their entries to accept the change",
+                naming::NAMES_PATH,
+                removed.len(),
+                removed.join(", ")
+            )
+            .into());
         }
+        fs::write(&path, names.to_json())?;
+        println!("cargo:warning=Recorded {} operation names in {}", names.names.len(), path.display());
+    } else if names.added > 0 {
+        println!(
+            "cargo:warning={} operations have no entry in {}, set CLOUDFLARE_API_UPDATE_OPERATION_NAMES=1 to record them",
+            names.added,
+            naming::NAMES_PATH
+        );
     }
+    if !removed.is_empty() {
+        println!(
+            "cargo:warning={} operations in {} are no longer in the spec: {}",
+            removed.len(),
+            naming::NAMES_PATH,
+            removed.join(", ")
+        );
+    }
+    Ok(())
+}
 
-    // Handle allOf - merge all schemas into one
-    if let Some(all_of) = schema.get("allOf").and_then(|a| a.as_array()).cloned() {
-        let mut merged = serde_json::json!({
-            "type": "object",
-            "properties": {}
-        });
-
-        // Merge all allOf items
-        for item in &all_of {
-            merge_into(&mut merged, item);
-        }
+/// Load the schema the client is generated from.
+///
+/// The vendored snapshot is used unless one of the following is set:
+///
+/// - `CLOUDFLARE_API_SCHEMA_PATH`: a local JSON (or `.json.gz`) file
+/// - `CLOUDFLARE_API_SCHEMA_URL`: a URL to download (requires `fetch-schema`)
+/// - the `fetch-schema` feature alone: download from [`DEFAULT_SCHEMA_URL`]
+///
+/// Overrides may be pinned with `CLOUDFLARE_API_SCHEMA_SHA256`. Downloads are
+/// never attempted on docs.rs or when cargo is configured to be offline.
+fn load_schema() -> Result<String, Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-env-changed=CLOUDFLARE_API_SCHEMA_PATH");
+    println!("cargo:rerun-if-env-changed=CLOUDFLARE_API_SCHEMA_URL");
+    println!("cargo:rerun-if-env-changed=CLOUDFLARE_API_SCHEMA_SHA256");
+    println!("cargo:rerun-if-env-changed=CARGO_NET_OFFLINE");
+    println!("cargo:rerun-if-env-changed=DOCS_RS");
 
-        // If we got something useful, replace the schema
-        if merged.get("properties").and_then(|p| p.as_object()).map(|o| !o.is_empty()).unwrap_or(false) {
-            *schema = merged;
-        } else {
-            // Fallback: use the first non-$ref schema or convert to generic object
-            let first_concrete = all_of.iter()
-                .find(|s| !s.get("$ref").is_some())
-                .cloned()
-                .unwrap_or(serde_json::json!({"type": "object"}));
-            *schema = first_concrete;
-        }
+    if let Some(path) = env::var_os("CLOUDFLARE_API_SCHEMA_PATH") {
+        let path = PathBuf::from(path);
+        println!("cargo:rerun-if-changed={}", path.display());
+        println!("cargo:warning=Using OpenAPI schema from {}", path.display());
 
-        // Continue processing the merged schema
-        simplify_schema(schema);
-        return;
+        let content = read_schema_file(&path)?;
+        verify_override_checksum(&content)?;
+        return Ok(content);
     }
 
-    // Handle oneOf/anyOf - just use the first option to keep it simple
-    if let Some(one_of) = schema.get("oneOf").and_then(|o| o.as_array()).cloned() {
-        if let Some(first) = one_of.first().cloned() {
-            *schema = first;
-            simplify_schema(schema);
-            return;
-        }
-    }
+    let url = env::var("CLOUDFLARE_API_SCHEMA_URL").ok().or_else(|| {
+        cfg!(feature = "fetch-schema").then(|| DEFAULT_SCHEMA_URL.to_string())
+    });
 
-    if let Some(any_of) = schema.get("anyOf").and_then(|o| o.as_array()).cloned() {
-        if let Some(first) = any_of.first().cloned() {
-            *schema = first;
-            simplify_schema(schema);
-            return;
+    if let Some(url) = url {
+        if network_allowed() {
+            let content = fetch_schema(&url)?;
+            verify_override_checksum(&content)?;
+            return Ok(content);
         }
+        println!(
+            "cargo:warning=Not downloading {} (offline or docs.rs build), using vendored snapshot",
+            url
+        );
     }
 
-    // Recursively process nested schemas
-    if let Some(properties) = schema.get_mut("properties").and_then(|p| p.as_object_mut()) {
-        for prop in properties.values_mut() {
-            simplify_schema(prop);
-        }
-    }
+    load_snapshot()
+}
 
-    if let Some(items) = schema.get_mut("items") {
-        simplify_schema(items);
-    }
+/// Read and verify the vendored schema snapshot.
+fn load_snapshot() -> Result<String, Box<dyn std::error::Error>> {
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR")?);
+    let snapshot_path = manifest_dir.join(SNAPSHOT_PATH);
+    let checksum_path = manifest_dir.join(SNAPSHOT_CHECKSUM_PATH);
+    println!("cargo:rerun-if-changed={}", snapshot_path.display());
+    println!("cargo:rerun-if-changed={}", checksum_path.display());
 
-    if let Some(additional) = schema.get_mut("additionalProperties") {
-        if additional.is_object() {
-            simplify_schema(additional);
-        }
-    }
+    let content = read_schema_file(&snapshot_path).inspect_err(|e| {
+        eprintln!("Failed to read vendored schema snapshot {:?}: {}", snapshot_path, e);
+        eprintln!("Run scripts/update-schema.sh to refresh it.");
+    })?;
+
+    let recorded = fs::read_to_string(&checksum_path)?;
+    let expected = recorded
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| format!("{:?} does not contain a checksum", checksum_path))?;
+    verify_checksum(&content, expected).inspect_err(|_| {
+        eprintln!("Vendored schema snapshot does not match {:?}", checksum_path);
+    })?;
+
+    Ok(content)
 }
 
-fn merge_into(target: &mut serde_json::Value, source: &serde_json::Value) {
-    // Skip $ref schemas - we can't merge them easily
-    if source.get("$ref").is_some() {
-        return;
-    }
+/// Read a schema file, transparently decompressing `.gz` files.
+fn read_schema_file(path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    let file = fs::File::open(path)?;
+    let mut content = String::new();
 
-    // Merge properties
-    if let Some(source_props) = source.get("properties").and_then(|p| p.as_object()) {
-        if let Some(target_props) = target.get_mut("properties").and_then(|p| p.as_object_mut()) {
-            for (key, value) in source_props {
-                target_props.insert(key.clone(), value.clone());
-            }
-        }
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        flate2::read::GzDecoder::new(file).read_to_string(&mut content)?;
+    } else {
+        std::io::BufReader::new(file).read_to_string(&mut content)?;
     }
 
-    // Merge required fields
-    if let Some(source_required) = source.get("required").and_then(|r| r.as_array()) {
-        let target_required = target
-            .as_object_mut()
-            .and_then(|o| o.entry("required").or_insert(serde_json::json!([])).as_array_mut());
-
-        if let Some(target_req) = target_required {
-            for item in source_required {
-                if !target_req.contains(item) {
-                    target_req.push(item.clone());
-                }
-            }
-        }
+    Ok(content)
+}
+
+/// Verify an overridden schema against `CLOUDFLARE_API_SCHEMA_SHA256`, if set.
+fn verify_override_checksum(content: &str) -> Result<(), Box<dyn std::error::Error>> {
+    match env::var("CLOUDFLARE_API_SCHEMA_SHA256") {
+        Ok(expected) => verify_checksum(content, expected.trim()),
+        Err(_) => Ok(()),
     }
+}
 
-    // Copy other fields if not present
-    if let Some(source_obj) = source.as_object() {
-        if let Some(target_obj) = target.as_object_mut() {
-            for (key, value) in source_obj {
-                if key != "properties" && key != "required" && !target_obj.contains_key(key) {
-                    target_obj.insert(key.clone(), value.clone());
-                }
-            }
-        }
+fn verify_checksum(content: &str, expected: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let actual = sha256_hex(content);
+
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(format!(
+            "OpenAPI schema checksum mismatch: expected {}, got {}",
+            expected, actual
+        )
+        .into());
     }
+
+    Ok(())
+}
+
+fn sha256_hex(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Whether the build script may touch the network.
+fn network_allowed() -> bool {
+    let offline = env::var("CARGO_NET_OFFLINE").is_ok_and(|v| v == "true" || v == "1");
+    let docs_rs = env::var_os("DOCS_RS").is_some();
+    !offline && !docs_rs
+}
+
+#[cfg(feature = "fetch-schema")]
+fn fetch_schema(url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    println!("cargo:warning=Downloading OpenAPI schema from {}", url);
+
+    let content = reqwest::blocking::get(url)?
+        .error_for_status()?
+        .text()?;
+
+    Ok(content)
+}
+
+#[cfg(not(feature = "fetch-schema"))]
+fn fetch_schema(url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    Err(format!(
+        "Downloading the OpenAPI schema from {} requires the `fetch-schema` feature",
+        url
+    )
+    .into())
 }
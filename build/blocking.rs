@@ -0,0 +1,240 @@
+//! Synchronous mirror of the generated client (`blocking` feature).
+//!
+//! Every operation builder in the generated `builder` module gets a wrapper
+//! in `blocking::builder` that forwards its setters and blocks on its async
+//! methods, and `blocking::Client` gets one method per operation returning
+//! the wrapper. The method surface is therefore the same as the async
+//! client's, minus the `.await`.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+/// Render `blocking.rs`, included by `src/blocking.rs`.
+pub fn blocking_client(tokens: &TokenStream) -> Result<String, syn::Error> {
+    let file: syn::File = syn::parse2(tokens.clone())?;
+
+    let mut methods = Vec::new();
+    let mut builder_items = Vec::new();
+    for item in &file.items {
+        match item {
+            // Operations grouped by tag into extension traits
+            syn::Item::Trait(item) => {
+                for item in &item.items {
+                    if let syn::TraitItem::Fn(function) = item {
+                        methods.extend(operation(&function.attrs, &function.sig));
+                    }
+                }
+            }
+            // Untagged operations on the client itself
+            syn::Item::Impl(item) if item.trait_.is_none() && is_client(&item.self_ty) => {
+                for item in &item.items {
+                    if let syn::ImplItem::Fn(function) = item {
+                        methods.extend(operation(&function.attrs, &function.sig));
+                    }
+                }
+            }
+            syn::Item::Mod(module) if module.ident == "builder" => {
+                if let Some((_, items)) = &module.content {
+                    builder_items = builders(items);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let code = quote! {
+        /// Blocking counterparts of the generated operation builders.
+        // `send` returns progenitor's error type, as the async builders do
+        #[allow(clippy::result_large_err)]
+        pub mod builder {
+            #(#builder_items)*
+        }
+
+        impl Client {
+            #(#methods)*
+        }
+    };
+
+    Ok(code.to_string())
+}
+
+/// A blocking client method for an operation method returning a builder.
+fn operation(attrs: &[syn::Attribute], sig: &syn::Signature) -> Option<TokenStream> {
+    let syn::ReturnType::Type(_, output) = &sig.output else {
+        return None;
+    };
+    let syn::Type::Path(output) = &**output else {
+        return None;
+    };
+    let segments: Vec<_> = output.path.segments.iter().collect();
+    let [module, builder] = segments.as_slice() else {
+        return None;
+    };
+    if module.ident != "builder" || sig.inputs.len() != 1 {
+        return None;
+    }
+
+    let docs = docs(attrs);
+    let name = &sig.ident;
+    let builder = &builder.ident;
+    Some(quote! {
+        #(#docs)*
+        pub fn #name(&self) -> builder::#builder<'_> {
+            builder::#builder::new(self)
+        }
+    })
+}
+
+/// Wrappers for the structs and impls of the generated `builder` module.
+fn builders(items: &[syn::Item]) -> Vec<TokenStream> {
+    let mut wrappers = Vec::new();
+
+    for item in items {
+        match item {
+            syn::Item::Use(item) => {
+                let mut item = item.clone();
+                item.attrs.retain(|attr| !attr.path().is_ident("allow"));
+                to_crate_root(&mut item.tree);
+                wrappers.push(quote!(#[allow(unused_imports)] #item));
+            }
+            syn::Item::Struct(item) => {
+                let Some(lifetime) = item.generics.lifetimes().next().map(|l| &l.lifetime) else {
+                    continue;
+                };
+                let name = &item.ident;
+                let generics = &item.generics;
+                let (_, type_generics, _) = item.generics.split_for_impl();
+                // The generated docs link relative to the async builder
+                let doc = format!("Blocking [`crate::builder::{}`].", name);
+                let derives = item
+                    .attrs
+                    .iter()
+                    .filter(|attr| attr.path().is_ident("derive"));
+                wrappers.push(quote! {
+                    #[doc = #doc]
+                    #(#derives)*
+                    pub struct #name #generics {
+                        inner: crate::builder::#name #type_generics,
+                        runtime: &#lifetime tokio::runtime::Runtime,
+                    }
+                });
+            }
+            syn::Item::Impl(item) if item.trait_.is_none() => {
+                let syn::Type::Path(self_ty) = &*item.self_ty else {
+                    continue;
+                };
+                let Some(builder) = self_ty.path.segments.last().map(|s| &s.ident) else {
+                    continue;
+                };
+                let Some(lifetime) = item.generics.lifetimes().next().map(|l| &l.lifetime) else {
+                    continue;
+                };
+                let functions = item.items.iter().filter_map(|item| match item {
+                    syn::ImplItem::Fn(function)
+                        if matches!(function.vis, syn::Visibility::Public(_)) =>
+                    {
+                        forward(function, builder, lifetime)
+                    }
+                    _ => None,
+                });
+                let generics = &item.generics;
+                wrappers.push(quote! {
+                    impl #generics #self_ty {
+                        #(#functions)*
+                    }
+                });
+            }
+            _ => {}
+        }
+    }
+
+    wrappers
+}
+
+/// Forward a builder method to the wrapped builder: `new` wraps a new
+/// builder, setters rewrap the builder they return, and async methods
+/// (`send`) block on the client's runtime.
+fn forward(
+    function: &syn::ImplItemFn,
+    builder: &syn::Ident,
+    lifetime: &syn::Lifetime,
+) -> Option<TokenStream> {
+    let docs = docs(&function.attrs);
+    let name = &function.sig.ident;
+
+    if name == "new" {
+        return Some(quote! {
+            #(#docs)*
+            pub fn new(client: &#lifetime super::Client) -> Self {
+                Self {
+                    inner: crate::builder::#builder::new(&client.inner),
+                    runtime: &client.runtime,
+                }
+            }
+        });
+    }
+
+    let mut sig = function.sig.clone();
+    let mut args = Vec::new();
+    for input in &mut sig.inputs {
+        match input {
+            syn::FnArg::Receiver(receiver) if receiver.reference.is_none() => {
+                receiver.mutability = None;
+            }
+            syn::FnArg::Typed(arg) => {
+                let syn::Pat::Ident(pat) = &mut *arg.pat else {
+                    return None;
+                };
+                pat.mutability = None;
+                args.push(pat.ident.clone());
+            }
+            _ => return None,
+        }
+    }
+
+    if sig.asyncness.take().is_some() {
+        return Some(quote! {
+            #(#docs)*
+            pub #sig {
+                self.runtime.block_on(self.inner.#name(#(#args),*))
+            }
+        });
+    }
+
+    let returns_self = matches!(
+        &sig.output,
+        syn::ReturnType::Type(_, ty) if matches!(&**ty, syn::Type::Path(p) if p.path.is_ident("Self"))
+    );
+    if !returns_self {
+        return None;
+    }
+    Some(quote! {
+        #(#docs)*
+        pub #sig {
+            Self {
+                inner: self.inner.#name(#(#args),*),
+                runtime: self.runtime,
+            }
+        }
+    })
+}
+
+/// Only the `#[doc]` attributes, including doc aliases.
+fn docs(attrs: &[syn::Attribute]) -> impl Iterator<Item = &syn::Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("doc"))
+}
+
+/// Rewrite `use super::...` to `use crate::...`; the generated builder
+/// module sits at the crate root.
+fn to_crate_root(tree: &mut syn::UseTree) {
+    if let syn::UseTree::Path(path) = tree
+        && path.ident == "super"
+    {
+        path.ident = format_ident!("crate");
+    }
+}
+
+/// Whether a type is the generated `Client`.
+fn is_client(ty: &syn::Type) -> bool {
+    matches!(ty, syn::Type::Path(path) if path.path.is_ident("Client"))
+}
@@ -0,0 +1,152 @@
+//! Record how the schema was changed to make it generate, so that fidelity
+//! can be tracked across schema revisions.
+//!
+//! The report is written to `$OUT_DIR/diagnostics.json` (machine readable)
+//! and `$OUT_DIR/diagnostics.txt` (human summary).
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::json;
+
+/// What happened to an operation or schema.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    /// A node was changed by a file in `overlays/`.
+    OverlayApplied,
+    /// An operation had no upstream `operationId`.
+    MissingOperationId,
+    /// A new operation name was taken and got a numeric suffix.
+    OperationNameCollision,
+    /// An operation was removed because its product area is not enabled.
+    FilteredOperation,
+    /// A `oneOf`/`anyOf` was replaced by `serde_json::Value`, or dropped in
+    /// favour of the schema next to it.
+    CollapsedUnion,
+    /// An `allOf` member `$ref` could not be resolved and was dropped.
+    UnresolvedRef,
+    /// Constraints that contradict an `enum` were removed.
+    EnumConstraintRemoved,
+    /// An OpenAPI 3.1 construct was rewritten into its OpenAPI 3.0 form.
+    Downgraded,
+    /// A request body media type Progenitor cannot send was dropped in
+    /// favour of one it can.
+    MediaTypeChanged,
+    /// A spec example that does not round-trip is listed as known bad.
+    KnownBadExample,
+}
+
+impl Kind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::OverlayApplied => "overlay-applied",
+            Kind::MissingOperationId => "missing-operation-id",
+            Kind::OperationNameCollision => "operation-name-collision",
+            Kind::FilteredOperation => "filtered-operation",
+            Kind::CollapsedUnion => "collapsed-union",
+            Kind::UnresolvedRef => "unresolved-ref",
+            Kind::EnumConstraintRemoved => "enum-constraint-removed",
+            Kind::Downgraded => "downgraded",
+            Kind::MediaTypeChanged => "media-type-changed",
+            Kind::KnownBadExample => "known-bad-example",
+        }
+    }
+}
+
+/// A single change made to the schema.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Diagnostic {
+    pub kind: Kind,
+    /// Operation (`GET /zones`) or schema location (`zone/properties/plan`).
+    pub location: String,
+    /// Why the change was made.
+    pub reason: String,
+}
+
+/// Every change made to the schema during a build.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    entries: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn push(&mut self, kind: Kind, location: impl Into<String>, reason: impl Into<String>) {
+        self.entries.push(Diagnostic {
+            kind,
+            location: location.into(),
+            reason: reason.into(),
+        });
+    }
+
+    fn counts(&self) -> BTreeMap<Kind, usize> {
+        let mut counts = BTreeMap::new();
+        for entry in &self.entries {
+            *counts.entry(entry.kind).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Write `diagnostics.json` and `diagnostics.txt` to `out_dir`.
+    ///
+    /// `schema_version` and `schema_sha256` identify the schema revision.
+    pub fn write(
+        &mut self,
+        out_dir: &Path,
+        schema_version: &str,
+        schema_sha256: &str,
+    ) -> std::io::Result<(PathBuf, PathBuf)> {
+        self.entries.sort();
+        let counts = self.counts();
+
+        let report = json!({
+            "schema": {
+                "version": schema_version,
+                "sha256": schema_sha256,
+            },
+            "summary": counts
+                .iter()
+                .map(|(kind, count)| (kind.as_str().to_string(), json!(count)))
+                .collect::<serde_json::Map<_, _>>(),
+            "entries": self
+                .entries
+                .iter()
+                .map(|entry| json!({
+                    "kind": entry.kind.as_str(),
+                    "location": entry.location,
+                    "reason": entry.reason,
+                }))
+                .collect::<Vec<_>>(),
+        });
+
+        let mut summary = format!("Schema {} (sha256 {})\n\n", schema_version, schema_sha256);
+        for (kind, count) in &counts {
+            summary.push_str(&format!("{:>6}  {}\n", count, kind.as_str()));
+        }
+        let mut current = None;
+        for entry in &self.entries {
+            if current != Some(entry.kind) {
+                summary.push_str(&format!("\n## {}\n\n", entry.kind.as_str()));
+                current = Some(entry.kind);
+            }
+            summary.push_str(&format!("{}: {}\n", entry.location, entry.reason));
+        }
+
+        let json_path = out_dir.join("diagnostics.json");
+        let text_path = out_dir.join("diagnostics.txt");
+        fs::write(&json_path, serde_json::to_string_pretty(&report)?)?;
+        fs::write(&text_path, summary)?;
+
+        Ok((json_path, text_path))
+    }
+
+    /// One-line summary for a build warning, e.g. `3 collapsed-union, 1 unresolved-ref`.
+    pub fn summary(&self) -> String {
+        self.counts()
+            .iter()
+            .filter(|(kind, _)| **kind != Kind::FilteredOperation)
+            .map(|(kind, count)| format!("{} {}", count, kind.as_str()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
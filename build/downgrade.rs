@@ -0,0 +1,348 @@
+//! Rewrite OpenAPI 3.1 constructs into their OpenAPI 3.0 equivalents.
+//!
+//! The spec is declared as OpenAPI 3.0 but parts of it use OpenAPI 3.1
+//! (JSON Schema 2020-12) keywords, which `openapiv3` cannot deserialize.
+//! Every schema is downgraded before anything else looks at it:
+//!
+//! - `type: [T, "null"]` becomes `type: T` with `nullable: true`; several
+//!   types become an `anyOf` with one member per type
+//! - `{type: "null"}` members of a `oneOf`/`anyOf` become `nullable: true`
+//! - `x-nullable: true` (Swagger 2.0) becomes `nullable: true`
+//! - `const: V` becomes `enum: [V]`
+//! - `examples: [V, ...]` becomes `example: V`
+//! - numeric `exclusiveMinimum: N` becomes `minimum: N` with
+//!   `exclusiveMinimum: true`, and likewise for `exclusiveMaximum`
+//! - a `$ref` with sibling keywords becomes `allOf: [{$ref}]` next to them,
+//!   unless they only annotate it, in which case they are dropped as
+//!   OpenAPI 3.0 ignores them
+//!
+//! Each rewrite is recorded in the diagnostics report.
+
+use serde_json::{Map, Value, json};
+
+use crate::diagnostics::{Diagnostics, Kind};
+use crate::visit::{for_each_schema, subschemas_mut};
+
+/// Keywords that may sit next to a `$ref` without changing what it refers to.
+const ANNOTATIONS: &[&str] = &[
+    "$comment",
+    "default",
+    "deprecated",
+    "description",
+    "example",
+    "examples",
+    "externalDocs",
+    "readOnly",
+    "summary",
+    "title",
+    "writeOnly",
+];
+
+/// Keywords that only constrain values of one JSON type, moved into the
+/// member of that type when a type array becomes an `anyOf`.
+const TYPE_KEYWORDS: &[(&str, &[&str])] = &[
+    (
+        "array",
+        &[
+            "items",
+            "prefixItems",
+            "contains",
+            "minItems",
+            "maxItems",
+            "uniqueItems",
+        ],
+    ),
+    (
+        "integer",
+        &[
+            "format",
+            "minimum",
+            "maximum",
+            "exclusiveMinimum",
+            "exclusiveMaximum",
+            "multipleOf",
+        ],
+    ),
+    (
+        "number",
+        &[
+            "format",
+            "minimum",
+            "maximum",
+            "exclusiveMinimum",
+            "exclusiveMaximum",
+            "multipleOf",
+        ],
+    ),
+    (
+        "object",
+        &[
+            "properties",
+            "patternProperties",
+            "additionalProperties",
+            "required",
+            "minProperties",
+            "maxProperties",
+        ],
+    ),
+    ("string", &["format", "pattern", "minLength", "maxLength"]),
+];
+
+/// Downgrade every schema in `spec`, and its declared version.
+pub fn downgrade_to_3_0(spec: &mut Value, diagnostics: &mut Diagnostics) {
+    if let Some(version) = spec.get_mut("openapi")
+        && let Some(declared) = version.as_str().filter(|v| v.starts_with("3.1"))
+    {
+        diagnostics.push(
+            Kind::Downgraded,
+            "openapi",
+            format!("version {} declared as 3.0.3", declared),
+        );
+        *version = json!("3.0.3");
+    }
+
+    for_each_schema(spec, &mut |schema, location| {
+        downgrade(schema, location, diagnostics)
+    });
+}
+
+fn downgrade(schema: &mut Value, location: &str, diagnostics: &mut Diagnostics) {
+    if let Some(obj) = schema.as_object_mut() {
+        let mut changes = Vec::new();
+        x_nullable(obj, &mut changes);
+        reference_siblings(obj, &mut changes);
+        type_array(obj, &mut changes);
+        null_members(obj, &mut changes);
+        constant(obj, &mut changes);
+        examples(obj, &mut changes);
+        exclusive_bounds(obj, &mut changes);
+
+        if !changes.is_empty() {
+            diagnostics.push(Kind::Downgraded, location, changes.join("; "));
+        }
+    }
+
+    for (path, child) in subschemas_mut(schema) {
+        downgrade(child, &format!("{}/{}", location, path), diagnostics);
+    }
+}
+
+fn x_nullable(obj: &mut Map<String, Value>, changes: &mut Vec<String>) {
+    let Some(nullable) = obj.remove("x-nullable") else {
+        return;
+    };
+    if nullable == Value::Bool(true) {
+        obj.insert("nullable".to_string(), json!(true));
+    }
+    changes.push("x-nullable replaced by nullable".to_string());
+}
+
+fn reference_siblings(obj: &mut Map<String, Value>, changes: &mut Vec<String>) {
+    if !obj.contains_key("$ref") || obj.len() == 1 {
+        return;
+    }
+
+    let annotates = |key: &String| ANNOTATIONS.contains(&key.as_str()) || key.starts_with("x-");
+    if obj.keys().filter(|key| *key != "$ref").all(annotates) {
+        let dropped: Vec<String> = obj.keys().filter(|key| *key != "$ref").cloned().collect();
+        obj.retain(|key, _| key == "$ref");
+        changes.push(format!("dropped {} next to $ref", dropped.join(", ")));
+        return;
+    }
+
+    let Some(reference) = obj.remove("$ref") else {
+        return;
+    };
+    let member = json!({ "$ref": reference });
+    match obj.get_mut("allOf") {
+        Some(Value::Array(members)) => members.insert(0, member),
+        _ => {
+            obj.insert("allOf".to_string(), json!([member]));
+        }
+    }
+    changes.push("$ref with siblings moved into allOf".to_string());
+}
+
+fn type_array(obj: &mut Map<String, Value>, changes: &mut Vec<String>) {
+    let Some(Value::Array(declared)) = obj.get("type") else {
+        return;
+    };
+    let declared: Vec<String> = declared
+        .iter()
+        .filter_map(|ty| ty.as_str())
+        .map(str::to_string)
+        .collect();
+    obj.remove("type");
+
+    let mut types: Vec<&str> = declared
+        .iter()
+        .map(String::as_str)
+        .filter(|ty| *ty != "null")
+        .collect();
+    if types.len() < declared.len() {
+        obj.insert("nullable".to_string(), json!(true));
+    }
+    // Integers are numbers too
+    if types.contains(&"number") {
+        types.retain(|ty| *ty != "integer");
+    }
+    types.dedup();
+
+    let change = format!("type [{}]", declared.join(", "));
+    match types.as_slice() {
+        [] => changes.push(format!("{} replaced by nullable", change)),
+        [ty] => {
+            obj.insert("type".to_string(), json!(ty));
+            changes.push(format!("{} replaced by type {}", change, ty));
+        }
+        _ => {
+            let Some(keyword) = ["anyOf", "oneOf"]
+                .into_iter()
+                .find(|keyword| !obj.contains_key(*keyword))
+            else {
+                changes.push(format!("{} dropped", change));
+                return;
+            };
+            let members = type_members(obj, &types);
+            obj.insert(keyword.to_string(), Value::Array(members));
+            changes.push(format!("{} replaced by {}", change, keyword));
+        }
+    }
+}
+
+/// One schema per type, each with the keywords (and `enum` values) of its
+/// type, which are removed from `obj`.
+fn type_members(obj: &mut Map<String, Value>, types: &[&str]) -> Vec<Value> {
+    let values = obj.remove("enum");
+
+    let mut members = Vec::new();
+    for ty in types {
+        let mut member = Map::new();
+        member.insert("type".to_string(), json!(ty));
+        for keyword in keywords_of(ty) {
+            if let Some(value) = obj.get(*keyword) {
+                member.insert(keyword.to_string(), value.clone());
+            }
+        }
+
+        if let Some(Value::Array(values)) = &values {
+            let values: Vec<Value> = values
+                .iter()
+                .filter(|value| has_type(value, ty))
+                .cloned()
+                .collect();
+            // The enum allows no value of this type
+            if values.is_empty() {
+                continue;
+            }
+            member.insert("enum".to_string(), Value::Array(values));
+        }
+        members.push(Value::Object(member));
+    }
+
+    for ty in types {
+        for keyword in keywords_of(ty) {
+            obj.remove(*keyword);
+        }
+    }
+    members
+}
+
+fn keywords_of(ty: &str) -> &'static [&'static str] {
+    TYPE_KEYWORDS
+        .iter()
+        .find(|(name, _)| *name == ty)
+        .map(|(_, keywords)| *keywords)
+        .unwrap_or_default()
+}
+
+fn has_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "array" => value.is_array(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "object" => value.is_object(),
+        "string" => value.is_string(),
+        _ => false,
+    }
+}
+
+fn null_members(obj: &mut Map<String, Value>, changes: &mut Vec<String>) {
+    let mut nullable = false;
+    for keyword in ["oneOf", "anyOf"] {
+        let Some(Value::Array(members)) = obj.get_mut(keyword) else {
+            continue;
+        };
+        let before = members.len();
+        members.retain(|member| !is_null_schema(member));
+        if members.len() == before {
+            continue;
+        }
+
+        nullable = true;
+        if members.is_empty() {
+            obj.remove(keyword);
+        }
+        changes.push(format!("null member of {} replaced by nullable", keyword));
+    }
+    if nullable {
+        obj.insert("nullable".to_string(), json!(true));
+    }
+}
+
+/// Whether `schema` only allows `null`.
+fn is_null_schema(schema: &Value) -> bool {
+    let only_null = |values: &Value| {
+        values
+            .as_array()
+            .is_some_and(|values| !values.is_empty() && values.iter().all(Value::is_null))
+    };
+    match schema.get("type") {
+        Some(Value::String(ty)) => ty == "null",
+        Some(Value::Array(types)) => !types.is_empty() && types.iter().all(|ty| *ty == "null"),
+        _ => {
+            schema.get("const").is_some_and(Value::is_null)
+                || schema.get("enum").is_some_and(only_null)
+        }
+    }
+}
+
+fn constant(obj: &mut Map<String, Value>, changes: &mut Vec<String>) {
+    let Some(value) = obj.remove("const") else {
+        return;
+    };
+    if !obj.contains_key("enum") {
+        obj.insert("enum".to_string(), json!([value]));
+    }
+    changes.push("const replaced by enum".to_string());
+}
+
+fn examples(obj: &mut Map<String, Value>, changes: &mut Vec<String>) {
+    if !obj.get("examples").is_some_and(Value::is_array) {
+        return;
+    }
+    let Some(Value::Array(examples)) = obj.remove("examples") else {
+        return;
+    };
+    if let Some(first) = examples.into_iter().next()
+        && !obj.contains_key("example")
+    {
+        obj.insert("example".to_string(), first);
+    }
+    changes.push("examples replaced by example".to_string());
+}
+
+fn exclusive_bounds(obj: &mut Map<String, Value>, changes: &mut Vec<String>) {
+    for (exclusive, bound) in [
+        ("exclusiveMinimum", "minimum"),
+        ("exclusiveMaximum", "maximum"),
+    ] {
+        let Some(value) = obj.get(exclusive).filter(|v| v.is_number()).cloned() else {
+            continue;
+        };
+        obj.insert(bound.to_string(), value);
+        obj.insert(exclusive.to_string(), json!(true));
+        changes.push(format!("numeric {} replaced by {}", exclusive, bound));
+    }
+}
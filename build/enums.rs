@@ -0,0 +1,215 @@
+//! Forward-compatible string enums.
+//!
+//! Cloudflare adds enum values (record types, plans, zone statuses) without
+//! notice, and a typify enum fails to deserialize a value it does not know.
+//! Every generated string enum therefore gets a catch-all variant,
+//! `Unknown(String)`, that holds any other value and serializes back to it:
+//!
+//! ```ignore
+//! match status {
+//!     ZoneStatus::Active => ...,
+//!     ZoneStatus::Pending | ZoneStatus::Initializing => ...,
+//!     ZoneStatus::Moved => ...,
+//!     ZoneStatus::Unknown(status) => ...,
+//! }
+//! ```
+//!
+//! An enum whose schema sets `x-exhaustive-enum: true` (usually through an
+//! overlay) keeps only its known variants, and rejects unknown values.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use proc_macro2::TokenStream;
+use quote::{ToTokens, format_ident, quote};
+use serde_json::Value;
+
+use crate::modules::type_name;
+
+/// Schema extension that keeps an enum closed.
+pub const EXHAUSTIVE_EXTENSION: &str = "x-exhaustive-enum";
+
+/// Type names of the component schemas marked with [`EXHAUSTIVE_EXTENSION`].
+pub fn exhaustive_enums(spec: &Value) -> BTreeSet<String> {
+    spec.pointer("/components/schemas")
+        .and_then(|s| s.as_object())
+        .into_iter()
+        .flatten()
+        .filter(|(_, schema)| {
+            schema.get(EXHAUSTIVE_EXTENSION).and_then(|e| e.as_bool()) == Some(true)
+        })
+        .map(|(name, _)| type_name(name))
+        .collect()
+}
+
+/// Add a catch-all variant to every string enum in `tokens` not named in
+/// `exhaustive`.
+pub fn open_enums(
+    tokens: TokenStream,
+    exhaustive: &BTreeSet<String>,
+) -> Result<TokenStream, syn::Error> {
+    let mut file: syn::File = syn::parse2(tokens)?;
+    open_items(&mut file.items, exhaustive);
+    Ok(quote!(#file))
+}
+
+fn open_items(items: &mut [syn::Item], exhaustive: &BTreeSet<String>) {
+    // Catch-all variant of each opened enum, by enum name
+    let mut opened = BTreeMap::new();
+
+    for item in items.iter_mut() {
+        match item {
+            syn::Item::Mod(module) => {
+                if let Some((_, items)) = &mut module.content {
+                    open_items(items, exhaustive);
+                }
+            }
+            syn::Item::Enum(item)
+                if is_string_enum(item) && !exhaustive.contains(&item.ident.to_string()) =>
+            {
+                let variant = open_enum(item);
+                opened.insert(item.ident.to_string(), variant);
+            }
+            _ => {}
+        }
+    }
+
+    // The generated `Display` and `FromStr` impls match on every variant
+    for item in items.iter_mut() {
+        let syn::Item::Impl(item) = item else {
+            continue;
+        };
+        let Some(variant) = type_ident(&item.self_ty).and_then(|name| opened.get(&name)) else {
+            continue;
+        };
+        let Some(trait_name) = item
+            .trait_
+            .as_ref()
+            .and_then(|(_, path, _)| path.segments.last())
+            .map(|segment| segment.ident.to_string())
+        else {
+            continue;
+        };
+
+        for item in &mut item.items {
+            let syn::ImplItem::Fn(function) = item else {
+                continue;
+            };
+            let Some(matched) = find_match(&mut function.block) else {
+                continue;
+            };
+            match trait_name.as_str() {
+                "Display" => {
+                    if let Some(formatter) = function.sig.inputs.iter().nth(1) {
+                        display_unknown(matched, variant, formatter);
+                    }
+                }
+                "FromStr" => parse_unknown(matched, variant),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Whether `item` is a typify string enum: unit variants only, serialized
+/// by serde.
+fn is_string_enum(item: &syn::ItemEnum) -> bool {
+    let derives_serde = item.attrs.iter().any(|attr| {
+        attr.path().is_ident("derive")
+            && attr
+                .meta
+                .to_token_stream()
+                .to_string()
+                .contains("Deserialize")
+    });
+
+    derives_serde
+        && !item.variants.is_empty()
+        && item
+            .variants
+            .iter()
+            .all(|variant| matches!(variant.fields, syn::Fields::Unit))
+}
+
+/// Add the catch-all variant, returning its name: `Unknown`, or
+/// `UnknownValue` if the enum has a known value called "unknown".
+fn open_enum(item: &mut syn::ItemEnum) -> syn::Ident {
+    let taken = |name: &str| item.variants.iter().any(|v| v.ident == name);
+    let variant = if taken("Unknown") {
+        format_ident!("UnknownValue")
+    } else {
+        format_ident!("Unknown")
+    };
+
+    item.variants.push(syn::parse_quote! {
+        /// A value that was not known when this client was generated.
+        #[serde(untagged)]
+        #variant(::std::string::String)
+    });
+
+    // The new variant owns a string, so the enum can no longer be `Copy`
+    for attr in &mut item.attrs {
+        if !attr.path().is_ident("derive") {
+            continue;
+        }
+        if let Ok(paths) = attr.parse_args_with(
+            syn::punctuated::Punctuated::<syn::Path, syn::Token![,]>::parse_terminated,
+        ) {
+            let paths = paths.into_iter().filter(|path| {
+                path.segments
+                    .last()
+                    .is_none_or(|segment| segment.ident != "Copy")
+            });
+            *attr = syn::parse_quote!(#[derive(#(#paths),*)]);
+        }
+    }
+
+    variant
+}
+
+/// Write the unknown value itself to `formatter`, the second argument of
+/// `fmt`.
+fn display_unknown(matched: &mut syn::ExprMatch, variant: &syn::Ident, formatter: &syn::FnArg) {
+    let syn::FnArg::Typed(formatter) = formatter else {
+        return;
+    };
+    let formatter = &formatter.pat;
+
+    // `match *self` binds by value, `match self` by reference
+    let arm: syn::Arm = if matches!(&*matched.expr, syn::Expr::Unary(_)) {
+        syn::parse_quote!(Self::#variant(ref value) => #formatter.write_str(value),)
+    } else {
+        syn::parse_quote!(Self::#variant(value) => #formatter.write_str(value),)
+    };
+    matched.arms.push(arm);
+}
+
+/// Parse any other value into the catch-all variant instead of failing.
+fn parse_unknown(matched: &mut syn::ExprMatch, variant: &syn::Ident) {
+    let value = &matched.expr;
+    let body: syn::Expr = syn::parse_quote!(Ok(Self::#variant(#value.to_string())));
+
+    match matched
+        .arms
+        .iter_mut()
+        .find(|arm| matches!(arm.pat, syn::Pat::Wild(_)))
+    {
+        Some(arm) => *arm.body = body,
+        None => matched.arms.push(syn::parse_quote!(_ => #body,)),
+    }
+}
+
+/// The first `match` of a function body.
+fn find_match(block: &mut syn::Block) -> Option<&mut syn::ExprMatch> {
+    block.stmts.iter_mut().find_map(|stmt| match stmt {
+        syn::Stmt::Expr(syn::Expr::Match(matched), _) => Some(matched),
+        _ => None,
+    })
+}
+
+/// The name of a plain type such as `DnsRecordType`.
+fn type_ident(ty: &syn::Type) -> Option<String> {
+    match ty {
+        syn::Type::Path(path) => path.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    }
+}
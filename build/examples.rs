@@ -0,0 +1,193 @@
+//! Round-trip cases for the examples in the spec's request and response
+//! bodies, checked by `tests/examples.rs`.
+//!
+//! An example is covered when its body schema is a reference to a component
+//! schema, so the generated type is known: the media type `example`, every
+//! `examples` value, and the `example` of the component schema itself.
+//! Examples of inline schemas are counted but not covered.
+//!
+//! Examples known not to round-trip are listed in
+//! `schema/known_bad_examples.json`, keyed by location with the reason, and
+//! recorded in the diagnostics report. The test accepts their failures, and
+//! fails once they round-trip so the entry is removed.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::diagnostics::{Diagnostics, Kind};
+use crate::filter::is_operation_method;
+use crate::modules::ModuleTree;
+
+/// Checked-in known-bad examples, relative to the crate root.
+pub const KNOWN_BAD_PATH: &str = "schema/known_bad_examples.json";
+
+/// Read the known-bad examples: location to the reason it fails.
+pub fn read_known_bad(path: &Path) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed={}", path.display());
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+
+    let content = fs::read_to_string(path)?;
+    let known_bad = serde_json::from_str(&content).map_err(|e| {
+        format!(
+            "{}: expected an object of locations and reasons: {}",
+            path.display(),
+            e
+        )
+    })?;
+    Ok(known_bad)
+}
+
+/// Render `EXAMPLES`: `(location, schema name, example JSON, round trip)`
+/// for every covered example, and `KNOWN_BAD`: `(location, reason)` for
+/// those of them in `known_bad`, which are recorded in `diagnostics`.
+pub fn example_table(
+    spec: &Value,
+    modules: &ModuleTree,
+    known_bad: &BTreeMap<String, String>,
+    diagnostics: &mut Diagnostics,
+) -> String {
+    let mut cases = BTreeSet::new();
+    let mut uncovered = 0;
+
+    if let Some(paths) = spec.get("paths").and_then(|p| p.as_object()) {
+        for (path, item) in paths {
+            let Some(operations) = item.as_object() else {
+                continue;
+            };
+            for (method, operation) in operations {
+                if !is_operation_method(method) {
+                    continue;
+                }
+                let operation_name = format!("{} {}", method.to_uppercase(), path);
+
+                let mut bodies = Vec::new();
+                if let Some(body) = operation.get("requestBody") {
+                    bodies.push((format!("{} request body", operation_name), body));
+                }
+                if let Some(responses) = operation.get("responses").and_then(|r| r.as_object()) {
+                    for (status, response) in responses {
+                        bodies.push((format!("{} {} response", operation_name, status), response));
+                    }
+                }
+
+                for (location, body) in bodies {
+                    let Some(media) = resolve(spec, body)
+                        .get("content")
+                        .and_then(|c| c.get("application/json"))
+                    else {
+                        continue;
+                    };
+                    let examples = media_examples(spec, media);
+
+                    let Some((schema, type_path)) = media
+                        .get("schema")
+                        .and_then(|s| s.get("$ref"))
+                        .and_then(|r| r.as_str())
+                        .and_then(|r| r.strip_prefix("#/components/schemas/"))
+                        .and_then(|name| Some((name, modules.type_path(name)?)))
+                    else {
+                        uncovered += examples.len();
+                        continue;
+                    };
+
+                    for (label, example) in examples {
+                        cases.insert((
+                            schema.to_string(),
+                            example.to_string(),
+                            format!("{} ({})", location, label),
+                            type_path.clone(),
+                        ));
+                    }
+                    if let Some(example) =
+                        spec.pointer(&format!("/components/schemas/{}/example", escape(schema)))
+                    {
+                        cases.insert((
+                            schema.to_string(),
+                            example.to_string(),
+                            format!("components.schemas.{} (example)", schema),
+                            type_path.clone(),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    // The same example of the same schema is checked once
+    let mut seen = BTreeSet::new();
+    cases.retain(|(schema, example, _, _)| seen.insert((schema.clone(), example.clone())));
+
+    let mut code = format!(
+        "// {} examples of inline schemas are not covered.\n\n\
+         /// Examples from the spec: location, schema name, example JSON, and a\n\
+         /// round trip through the schema's generated type.\n\
+         const EXAMPLES: &[(&str, &str, &str, RoundTrip)] = &[\n",
+        uncovered
+    );
+    let mut known = Vec::new();
+    for (schema, example, location, type_path) in cases {
+        if let Some(reason) = known_bad.get(&location) {
+            diagnostics.push(Kind::KnownBadExample, location.as_str(), reason.as_str());
+            known.push((location.clone(), reason));
+        }
+
+        let type_path = type_path.replacen("crate::", "cloudflare_api::", 1);
+        code.push_str(&format!(
+            "    ({:?}, {:?}, {:?}, round_trip::<{}>),\n",
+            location, schema, example, type_path
+        ));
+    }
+    code.push_str("];\n\n");
+
+    code.push_str(&format!(
+        "/// Examples known not to round-trip, from {}: location and reason.\n\
+         const KNOWN_BAD: &[(&str, &str)] = &[\n",
+        KNOWN_BAD_PATH
+    ));
+    for (location, reason) in known {
+        code.push_str(&format!("    ({:?}, {:?}),\n", location, reason));
+    }
+    code.push_str("];\n");
+
+    code
+}
+
+/// The media type's `example` and `examples` values, with their labels.
+fn media_examples<'a>(spec: &'a Value, media: &'a Value) -> Vec<(String, &'a Value)> {
+    let mut examples = Vec::new();
+
+    if let Some(example) = media.get("example") {
+        examples.push(("example".to_string(), example));
+    }
+    if let Some(named) = media.get("examples").and_then(|e| e.as_object()) {
+        for (name, example) in named {
+            // External examples (`externalValue`) are not fetched
+            if let Some(value) = resolve(spec, example).get("value") {
+                examples.push((format!("examples.{}", name), value));
+            }
+        }
+    }
+
+    examples
+}
+
+/// Follow a local `$ref`, returning the value itself otherwise.
+fn resolve<'a>(spec: &'a Value, value: &'a Value) -> &'a Value {
+    match value.get("$ref").and_then(|r| r.as_str()) {
+        Some(reference) => reference
+            .strip_prefix('#')
+            .and_then(|pointer| spec.pointer(pointer))
+            .unwrap_or(value),
+        None => value,
+    }
+}
+
+/// Escape a name for use in a JSON pointer.
+fn escape(name: &str) -> String {
+    name.replace('~', "~0").replace('/', "~1")
+}
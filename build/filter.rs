@@ -0,0 +1,291 @@
+//! Prune the schema down to the product areas enabled as cargo features.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+
+use serde_json::Value;
+
+use crate::diagnostics::{Diagnostics, Kind};
+
+/// A Cloudflare product area that can be enabled with a cargo feature.
+struct ProductArea {
+    /// Cargo feature name.
+    feature: &'static str,
+    /// Operations tagged with one of these tags are kept.
+    tags: &'static [&'static str],
+    /// Operations tagged with one of these tags, or with a tag starting with
+    /// one of them followed by a space, are kept: `Workers KV` keeps
+    /// `Workers KV Namespace`, and `Worker` does not keep `Workers KV`.
+    tag_prefixes: &'static [&'static str],
+    /// Operations whose path starts with one of these are kept. Path
+    /// parameters are written as `{}`.
+    paths: &'static [&'static str],
+}
+
+const PRODUCT_AREAS: &[ProductArea] = &[
+    ProductArea {
+        feature: "accounts",
+        tags: &["Accounts", "Account Members", "Account Roles", "Account Subscriptions"],
+        tag_prefixes: &[],
+        paths: &["/accounts/{}/members", "/accounts/{}/roles", "/accounts/{}/subscriptions"],
+    },
+    ProductArea {
+        feature: "dns",
+        tags: &[],
+        tag_prefixes: &["DNS"],
+        paths: &[
+            "/zones/{}/dns_records",
+            "/zones/{}/dns_settings",
+            "/zones/{}/dnssec",
+            "/accounts/{}/dns_settings",
+            "/accounts/{}/dns_firewall",
+        ],
+    },
+    ProductArea {
+        feature: "zones",
+        tags: &["Zone", "Zone Holds", "Zone Settings", "Zone Subscription"],
+        tag_prefixes: &[],
+        paths: &[
+            "/zones/{}/settings",
+            "/zones/{}/activation_check",
+            "/zones/{}/purge_cache",
+            "/zones/{}/hold",
+            "/zones/{}/subscription",
+        ],
+    },
+    ProductArea {
+        feature: "workers",
+        tags: &[],
+        tag_prefixes: &["Worker"],
+        paths: &["/accounts/{}/workers", "/zones/{}/workers"],
+    },
+    ProductArea {
+        feature: "kv",
+        tags: &[],
+        tag_prefixes: &["Workers KV"],
+        paths: &["/accounts/{}/storage/kv"],
+    },
+    ProductArea {
+        feature: "r2",
+        tags: &[],
+        tag_prefixes: &["R2"],
+        paths: &["/accounts/{}/r2"],
+    },
+    ProductArea {
+        feature: "zero-trust",
+        tags: &[],
+        tag_prefixes: &["Zero Trust", "Access", "Gateway", "Devices", "Tunnel", "DLP"],
+        paths: &[
+            "/accounts/{}/access",
+            "/accounts/{}/gateway",
+            "/accounts/{}/devices",
+            "/accounts/{}/cfd_tunnel",
+            "/accounts/{}/teamnet",
+            "/accounts/{}/dlp",
+            "/accounts/{}/zerotrust",
+        ],
+    },
+];
+
+/// Paths that belong to an area even though they are only a prefix of
+/// other areas' paths, e.g. `/zones/{}` for `zones`.
+const EXACT_PATHS: &[(&str, &str)] = &[
+    ("accounts", "/accounts"),
+    ("accounts", "/accounts/{}"),
+    ("zones", "/zones"),
+    ("zones", "/zones/{}"),
+];
+
+/// The product area features (and `full`) enabled for this build.
+pub fn enabled_features() -> Vec<&'static str> {
+    std::iter::once("full")
+        .chain(PRODUCT_AREAS.iter().map(|area| area.feature))
+        .filter(|feature| feature_enabled(feature))
+        .collect()
+}
+
+/// Remove every operation that is not part of a product area in `features`,
+/// then drop the components that are no longer reachable from the kept paths.
+///
+/// Does nothing when `features` contains `full`.
+pub fn filter_product_areas(
+    spec: &mut Value,
+    features: &[&str],
+    diagnostics: &mut Diagnostics,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if features.contains(&"full") {
+        return Ok(());
+    }
+
+    let enabled: Vec<&ProductArea> = PRODUCT_AREAS
+        .iter()
+        .filter(|area| features.contains(&area.feature))
+        .collect();
+
+    if enabled.is_empty() {
+        let features: Vec<&str> = PRODUCT_AREAS.iter().map(|a| a.feature).collect();
+        return Err(format!(
+            "No API features enabled; enable `full` or at least one of: {}",
+            features.join(", ")
+        )
+        .into());
+    }
+
+    if let Some(paths) = spec.get_mut("paths").and_then(|p| p.as_object_mut()) {
+        paths.retain(|path_name, path_item| {
+            let normalized = normalize_path(path_name);
+
+            if let Some(operations) = path_item.as_object_mut() {
+                operations.retain(|method, operation| {
+                    let keep = !is_operation_method(method)
+                        || operation_enabled(&normalized, operation, &enabled);
+                    if !keep {
+                        diagnostics.push(
+                            Kind::FilteredOperation,
+                            format!("{} {}", method.to_uppercase(), path_name),
+                            "product area not enabled",
+                        );
+                    }
+                    keep
+                });
+            }
+
+            path_item
+                .as_object()
+                .is_some_and(|ops| ops.keys().any(|k| is_operation_method(k)))
+        });
+    }
+
+    prune_components(spec);
+
+    Ok(())
+}
+
+fn feature_enabled(feature: &str) -> bool {
+    let var = format!("CARGO_FEATURE_{}", feature.to_uppercase().replace('-', "_"));
+    env::var_os(var).is_some()
+}
+
+pub fn is_operation_method(method: &str) -> bool {
+    ["get", "put", "post", "delete", "options", "head", "patch", "trace"].contains(&method)
+}
+
+/// Replace every `{parameter}` in a path with `{}`.
+fn normalize_path(path: &str) -> String {
+    let mut normalized = String::with_capacity(path.len());
+    let mut in_param = false;
+
+    for c in path.chars() {
+        match c {
+            '{' => {
+                in_param = true;
+                normalized.push('{');
+            }
+            '}' => {
+                in_param = false;
+                normalized.push('}');
+            }
+            _ if in_param => {}
+            _ => normalized.push(c),
+        }
+    }
+
+    normalized
+}
+
+fn operation_enabled(path: &str, operation: &Value, enabled: &[&ProductArea]) -> bool {
+    let tags: Vec<&str> = operation
+        .get("tags")
+        .and_then(|t| t.as_array())
+        .map(|tags| tags.iter().filter_map(|t| t.as_str()).collect())
+        .unwrap_or_default();
+
+    enabled.iter().any(|area| {
+        let by_tag = tags.iter().any(|tag| {
+            area.tags.contains(tag)
+                || area.tag_prefixes.iter().any(|prefix| {
+                    tag.strip_prefix(prefix)
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+                })
+        });
+        let by_path = area.paths.iter().any(|prefix| {
+            path == *prefix || path.starts_with(&format!("{}/", prefix))
+        });
+        let exact = EXACT_PATHS
+            .iter()
+            .any(|(feature, exact)| *feature == area.feature && path == *exact);
+
+        by_tag || by_path || exact
+    })
+}
+
+/// Keep only the components transitively referenced from `paths`.
+fn prune_components(spec: &mut Value) {
+    let mut reachable: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut pending = Vec::new();
+
+    if let Some(paths) = spec.get("paths") {
+        collect_refs(paths, &mut pending);
+    }
+
+    while let Some(reference) = pending.pop() {
+        let Some((kind, name)) = parse_component_ref(&reference) else {
+            continue;
+        };
+
+        if !reachable.entry(kind.clone()).or_default().insert(name.clone()) {
+            continue;
+        }
+
+        let component = spec
+            .get("components")
+            .and_then(|c| c.get(&kind))
+            .and_then(|k| k.get(&name));
+
+        if let Some(component) = component {
+            collect_refs(component, &mut pending);
+        }
+    }
+
+    if let Some(components) = spec.get_mut("components").and_then(|c| c.as_object_mut()) {
+        for (kind, entries) in components.iter_mut() {
+            // Security schemes are referenced by name, not by `$ref`
+            if kind == "securitySchemes" {
+                continue;
+            }
+
+            if let Some(entries) = entries.as_object_mut() {
+                let keep = reachable.get(kind.as_str());
+                entries.retain(|name, _| keep.is_some_and(|k| k.contains(name)));
+            }
+        }
+    }
+}
+
+/// Collect every `$ref` string below `value`.
+pub fn collect_refs(value: &Value, refs: &mut Vec<String>) {
+    match value {
+        Value::Object(obj) => {
+            for (key, child) in obj {
+                match (key.as_str(), child) {
+                    ("$ref", Value::String(reference)) => refs.push(reference.clone()),
+                    _ => collect_refs(child, refs),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_refs(item, refs);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Split `#/components/<kind>/<name>` into its kind and (unescaped) name.
+pub fn parse_component_ref(reference: &str) -> Option<(String, String)> {
+    let rest = reference.strip_prefix("#/components/")?;
+    let (kind, name) = rest.split_once('/')?;
+    let name = name.replace("~1", "/").replace("~0", "~");
+    Some((kind.to_string(), name))
+}
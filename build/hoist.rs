@@ -0,0 +1,399 @@
+//! Move inline object and enum schemas into named components.
+//!
+//! Typify names an inline schema after the first place it meets it, so the
+//! generated types get names that change whenever the spec shifts. Every
+//! inline object (a schema with `properties`) and enum is moved to
+//! `components.schemas` under a name derived from where it is used:
+//!
+//! - `<operation>_request` for a request body, `<operation>_response` for the
+//!   first 2xx response and `<operation>_response_<status>` for the others
+//! - `<operation>_<parameter>` for a parameter
+//! - `<parent>_<property>`, `<parent>_item` and `<parent>_value` for the
+//!   properties, array items and map values of a schema
+//! - `<parent>_<title>` for a `oneOf`/`anyOf` member, or `<parent>_<tag>`
+//!   after the value of its discriminator (or other one-value enum property)
+//!   when it has no title, and `<parent>_variant_<n>` when it has neither
+//!
+//! Structurally identical schemas (ignoring descriptions, examples and `x-`
+//! extensions other than `x-exhaustive-enum`) share one component: an existing component of the same
+//! shape, or else the one with the shortest candidate name.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::{Value, json};
+
+use crate::enums::EXHAUSTIVE_EXTENSION;
+use crate::filter::is_operation_method;
+use crate::visit::subschemas_mut;
+
+/// Prefix of the references to hoisted schemas before they are named.
+const PLACEHOLDER: &str = "#/x-hoisted/";
+
+/// Keywords that document a schema without changing its shape.
+const ANNOTATIONS: &[&str] = &[
+    "description",
+    "title",
+    "example",
+    "examples",
+    "externalDocs",
+];
+
+/// Hoist the inline schemas of `spec`.
+pub fn hoist_inline_schemas(spec: &mut Value) {
+    let mut hoister = Hoister::default();
+
+    if let Some(components) = spec.get_mut("components").and_then(|c| c.as_object_mut()) {
+        for (kind, entries) in components.iter_mut() {
+            let Some(entries) = entries.as_object_mut() else {
+                continue;
+            };
+            for (name, entry) in entries.iter_mut() {
+                match kind.as_str() {
+                    // Already named; only their nested schemas are hoisted
+                    "schemas" => hoister.visit(entry, name, false),
+                    "parameters" => {
+                        if let Some(schema) = entry.get_mut("schema") {
+                            hoister.visit(schema, name, true);
+                        }
+                    }
+                    "requestBodies" => hoister.content(entry, &format!("{}_request", name)),
+                    "responses" => hoister.content(entry, &format!("{}_response", name)),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    if let Some(paths) = spec.get_mut("paths").and_then(|p| p.as_object_mut()) {
+        for item in paths.values_mut().filter_map(|item| item.as_object_mut()) {
+            for (method, operation) in item.iter_mut() {
+                if is_operation_method(method) {
+                    hoister.operation(operation);
+                }
+            }
+        }
+    }
+
+    hoister.name_components(spec)
+}
+
+/// Hoisted schemas, grouped by shape.
+#[derive(Default)]
+struct Hoister {
+    /// Shape of each group to its index in `groups`.
+    shapes: BTreeMap<String, usize>,
+    groups: Vec<Group>,
+}
+
+struct Group {
+    shape: String,
+    /// The first schema found with this shape.
+    schema: Value,
+    /// Names derived from every place the shape was found.
+    candidates: BTreeSet<String>,
+}
+
+impl Hoister {
+    fn operation(&mut self, operation: &mut Value) {
+        let Some(operation_id) = operation
+            .get("operationId")
+            .and_then(|id| id.as_str())
+            .map(str::to_string)
+        else {
+            return;
+        };
+
+        if let Some(parameters) = operation
+            .get_mut("parameters")
+            .and_then(|p| p.as_array_mut())
+        {
+            for parameter in parameters {
+                let Some(name) = parameter.get("name").and_then(|n| n.as_str()) else {
+                    continue;
+                };
+                let name = format!("{}_{}", operation_id, name);
+                if let Some(schema) = parameter.get_mut("schema") {
+                    self.visit(schema, &name, true);
+                }
+            }
+        }
+
+        if let Some(body) = operation.get_mut("requestBody") {
+            self.content(body, &format!("{}_request", operation_id));
+        }
+
+        if let Some(responses) = operation
+            .get_mut("responses")
+            .and_then(|r| r.as_object_mut())
+        {
+            let success = responses
+                .keys()
+                .filter(|s| s.starts_with('2'))
+                .min()
+                .cloned();
+            for (status, response) in responses.iter_mut() {
+                let name = if Some(status) == success.as_ref() {
+                    format!("{}_response", operation_id)
+                } else {
+                    format!("{}_response_{}", operation_id, status.to_lowercase())
+                };
+                self.content(response, &name);
+            }
+        }
+    }
+
+    /// The schema of each media type of a request or response body.
+    fn content(&mut self, body: &mut Value, name: &str) {
+        let Some(content) = body.get_mut("content").and_then(|c| c.as_object_mut()) else {
+            return;
+        };
+        for media in content.values_mut() {
+            if let Some(schema) = media.get_mut("schema") {
+                self.visit(schema, name, true);
+            }
+        }
+    }
+
+    /// Hoist the nested schemas of `schema`, and with `hoist` the schema
+    /// itself, replacing each with a placeholder reference.
+    fn visit(&mut self, schema: &mut Value, name: &str, hoist: bool) {
+        let discriminator = schema
+            .pointer("/discriminator/propertyName")
+            .and_then(|p| p.as_str())
+            .map(str::to_string);
+
+        // Innermost first, so that identical children make parents identical
+        for (path, child) in subschemas_mut(schema) {
+            if let Some(suffix) = child_suffix(&path, child, discriminator.as_deref()) {
+                self.visit(child, &format!("{}_{}", name, suffix), true);
+            }
+        }
+
+        if !hoist || !is_hoistable(schema) {
+            return;
+        }
+
+        let shape = shape(schema);
+        let index = match self.shapes.get(&shape) {
+            Some(&index) => index,
+            None => {
+                self.shapes.insert(shape.clone(), self.groups.len());
+                self.groups.push(Group {
+                    shape,
+                    schema: schema.take(),
+                    candidates: BTreeSet::new(),
+                });
+                self.groups.len() - 1
+            }
+        };
+        self.groups[index].candidates.insert(sanitize(name));
+        *schema = json!({ "$ref": format!("{}{}", PLACEHOLDER, index) });
+    }
+
+    /// Name every group, add the new components and point the placeholder
+    /// references at them.
+    fn name_components(self, spec: &mut Value) {
+        if self.groups.is_empty() {
+            return;
+        }
+
+        let schemas = spec
+            .as_object_mut()
+            .and_then(|s| {
+                s.entry("components")
+                    .or_insert_with(|| json!({}))
+                    .as_object_mut()
+            })
+            .and_then(|c| {
+                c.entry("schemas")
+                    .or_insert_with(|| json!({}))
+                    .as_object_mut()
+            });
+        let Some(schemas) = schemas else {
+            return;
+        };
+
+        // Existing components, by shape
+        let mut existing = BTreeMap::new();
+        for (name, schema) in schemas.iter() {
+            existing
+                .entry(shape(schema))
+                .or_insert_with(|| name.clone());
+        }
+
+        let mut taken: BTreeSet<String> = schemas.keys().cloned().collect();
+        let mut names = Vec::new();
+        for group in self.groups {
+            if let Some(name) = existing.get(&group.shape) {
+                names.push(name.clone());
+                continue;
+            }
+
+            let name = choose_name(&group.candidates, &taken);
+            taken.insert(name.clone());
+            schemas.insert(name.clone(), group.schema);
+            names.push(name);
+        }
+
+        resolve_placeholders(spec, &names);
+    }
+}
+
+/// The name suffix of a nested schema that is hoisted, by its path relative
+/// to the parent (see [`subschemas_mut`]). Union members are hoisted after
+/// the union normalisation, so they are complete objects by now.
+fn child_suffix(path: &str, child: &Value, discriminator: Option<&str>) -> Option<String> {
+    if let Some(index) = path
+        .strip_prefix("oneOf/")
+        .or_else(|| path.strip_prefix("anyOf/"))
+    {
+        return Some(
+            member_suffix(child, discriminator).unwrap_or_else(|| format!("variant_{}", index)),
+        );
+    }
+    match path {
+        "items" => Some("item".to_string()),
+        "additionalProperties" => Some("value".to_string()),
+        _ => path.strip_prefix("properties/").map(str::to_string),
+    }
+}
+
+/// A union member's `title`, or else the value of its tag: the property
+/// named by the union's discriminator, or the first one-value string enum.
+fn member_suffix(member: &Value, discriminator: Option<&str>) -> Option<String> {
+    if let Some(title) = member.get("title").and_then(|t| t.as_str())
+        && !title.is_empty()
+    {
+        return Some(title.to_lowercase());
+    }
+
+    let properties = member.get("properties")?.as_object()?;
+    let tag = |schema: &Value| match schema.get("enum")?.as_array()?.as_slice() {
+        [Value::String(value)] => Some(value.to_lowercase()),
+        _ => None,
+    };
+    match discriminator {
+        Some(name) => tag(properties.get(name)?),
+        None => properties.values().find_map(tag),
+    }
+}
+
+/// Whether `schema` is an inline object or enum worth a name of its own.
+///
+/// Nullable schemas stay inline, as a reference cannot carry `nullable`.
+fn is_hoistable(schema: &Value) -> bool {
+    let Some(obj) = schema.as_object() else {
+        return false;
+    };
+    if ["$ref", "allOf", "oneOf", "anyOf"]
+        .iter()
+        .any(|keyword| obj.contains_key(*keyword))
+        || obj.get("nullable").and_then(|n| n.as_bool()) == Some(true)
+    {
+        return false;
+    }
+
+    let has_properties = obj
+        .get("properties")
+        .and_then(|p| p.as_object())
+        .is_some_and(|p| !p.is_empty());
+    has_properties || obj.get("enum").is_some_and(|e| e.is_array())
+}
+
+/// A canonical string for the shape of a schema: annotations and `x-`
+/// extensions removed and object keys sorted.
+fn shape(schema: &Value) -> String {
+    let mut schema = schema.clone();
+    strip_annotations(&mut schema);
+    canonical(&schema)
+}
+
+fn strip_annotations(schema: &mut Value) {
+    if let Some(obj) = schema.as_object_mut() {
+        obj.retain(|key, _| {
+            !ANNOTATIONS.contains(&key.as_str())
+                && (!key.starts_with("x-") || key == EXHAUSTIVE_EXTENSION)
+        });
+    }
+    for (_, child) in subschemas_mut(schema) {
+        strip_annotations(child);
+    }
+}
+
+fn canonical(value: &Value) -> String {
+    match value {
+        Value::Object(obj) => {
+            let sorted: BTreeMap<&String, String> =
+                obj.iter().map(|(k, v)| (k, canonical(v))).collect();
+            let fields: Vec<String> = sorted
+                .into_iter()
+                .map(|(k, v)| format!("{}:{}", Value::String(k.clone()), v))
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(canonical).collect();
+            format!("[{}]", items.join(","))
+        }
+        _ => value.to_string(),
+    }
+}
+
+/// The shortest (then alphabetically first) free candidate, or the first
+/// candidate with a numeric suffix if all are taken.
+fn choose_name(candidates: &BTreeSet<String>, taken: &BTreeSet<String>) -> String {
+    let mut candidates: Vec<&String> = candidates.iter().collect();
+    candidates.sort_by_key(|name| (name.len(), name.as_str()));
+
+    if let Some(name) = candidates
+        .iter()
+        .find(|name| !taken.contains(name.as_str()))
+    {
+        return name.to_string();
+    }
+
+    let base = candidates[0];
+    (2..)
+        .map(|n| format!("{}_{}", base, n))
+        .find(|name| !taken.contains(name))
+        .unwrap_or_else(|| base.clone())
+}
+
+/// Keep only characters that need no escaping in a reference and that
+/// typify turns into an identifier.
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Point every placeholder reference below `value` at the named component.
+fn resolve_placeholders(value: &mut Value, names: &[String]) {
+    match value {
+        Value::Object(obj) => {
+            if let Some(Value::String(reference)) = obj.get_mut("$ref")
+                && let Some(name) = reference
+                    .strip_prefix(PLACEHOLDER)
+                    .and_then(|index| index.parse::<usize>().ok())
+                    .and_then(|index| names.get(index))
+            {
+                *reference = format!("#/components/schemas/{}", name);
+            }
+            for child in obj.values_mut() {
+                resolve_placeholders(child, names);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                resolve_placeholders(item, names);
+            }
+        }
+        _ => {}
+    }
+}
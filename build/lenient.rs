@@ -0,0 +1,265 @@
+//! Forgiving types for the `lenient` feature.
+//!
+//! The live API returns fields the spec does not declare and `null` for
+//! fields it declares required. With the feature:
+//!
+//! - every required property (except union tags) is also nullable, so every
+//!   field is an `Option` and a missing or `null` value deserializes to
+//!   `None`. Properties stay required, so request builders still insist on
+//!   them
+//! - every generated struct gets an `extra` field holding the fields it does
+//!   not declare, which are serialized back:
+//!
+//! ```ignore
+//! #[serde(flatten)]
+//! pub extra: ::serde_json::Map<::std::string::String, ::serde_json::Value>,
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use proc_macro2::TokenStream;
+use quote::{ToTokens, format_ident, quote};
+use serde_json::{Value, json};
+use syn::visit_mut::{self, VisitMut};
+
+use crate::visit::{for_each_schema, subschemas_mut};
+
+/// Names tried for the field of undeclared fields, in order.
+const EXTRA_FIELDS: &[&str] = &["extra", "unknown_fields"];
+
+/// Make every required property of every schema in `spec` nullable.
+pub fn relax_required(spec: &mut Value) {
+    for_each_schema(spec, &mut |schema, _| relax(schema));
+}
+
+fn relax(schema: &mut Value) {
+    if let Some(obj) = schema.as_object_mut() {
+        let required: BTreeSet<String> = obj
+            .get("required")
+            .and_then(|r| r.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|name| name.as_str())
+            .map(str::to_string)
+            .collect();
+
+        if let Some(properties) = obj.get_mut("properties").and_then(|p| p.as_object_mut()) {
+            for (name, property) in properties.iter_mut() {
+                // A property with a single allowed value tags a union member,
+                // which typify only recognises while it is not nullable
+                if required.contains(name) && !is_tag(property) {
+                    make_nullable(property);
+                }
+            }
+        }
+    }
+
+    for (_, child) in subschemas_mut(schema) {
+        relax(child);
+    }
+}
+
+/// `nullable` next to a `$ref` is ignored, so a reference is wrapped in an
+/// `allOf` first.
+fn make_nullable(property: &mut Value) {
+    let Some(obj) = property.as_object_mut() else {
+        return;
+    };
+    if obj.is_empty() || obj.get("nullable") == Some(&Value::Bool(true)) {
+        return;
+    }
+
+    if let Some(reference) = obj.remove("$ref") {
+        obj.insert("allOf".to_string(), json!([{ "$ref": reference }]));
+    }
+    obj.insert("nullable".to_string(), Value::Bool(true));
+}
+
+/// `const` was rewritten to a one-value `enum` by `build/downgrade.rs`.
+fn is_tag(property: &Value) -> bool {
+    property
+        .get("enum")
+        .and_then(|e| e.as_array())
+        .is_some_and(|values| values.len() == 1)
+}
+
+/// Give every struct in `tokens` that serde deserializes a field holding the
+/// fields it does not declare.
+pub fn capture_unknown_fields(tokens: TokenStream) -> Result<TokenStream, syn::Error> {
+    let mut file: syn::File = syn::parse2(tokens)?;
+    capture_items(&mut file.items, &BTreeMap::new());
+    Ok(quote!(#file))
+}
+
+/// `parent` holds the structs changed in the parent module (typify's
+/// `builder` module builds `super::Name`), by name, with their new field.
+fn capture_items(items: &mut [syn::Item], parent: &BTreeMap<String, syn::Ident>) {
+    let mut captured = BTreeMap::new();
+    for item in items.iter_mut() {
+        if let syn::Item::Struct(item) = item
+            && let Some(field) = capture_struct(item)
+        {
+            captured.insert(item.ident.to_string(), field);
+        }
+    }
+
+    for item in items.iter_mut() {
+        match item {
+            syn::Item::Mod(module) => {
+                if let Some((_, items)) = &mut module.content {
+                    capture_items(items, &captured);
+                }
+            }
+            // Struct literals of the changed structs, in `Default` impls and
+            // in the builders' conversions, need the new field
+            syn::Item::Impl(item) => {
+                let target = match path_idents(&item.self_ty).as_slice() {
+                    [name] => captured.get(name).map(|field| (name.clone(), field, false)),
+                    [module, name] if module == "super" => {
+                        parent.get(name).map(|field| (name.clone(), field, true))
+                    }
+                    _ => None,
+                };
+                if let Some((name, field, in_parent)) = target {
+                    let mut literals = Literals {
+                        name,
+                        in_parent,
+                        field: field.clone(),
+                    };
+                    literals.visit_item_impl_mut(item);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Add the field for undeclared fields to `item`, returning its name, if
+/// serde deserializes `item` and nothing else captures them yet.
+fn capture_struct(item: &mut syn::ItemStruct) -> Option<syn::Ident> {
+    let derives_deserialize = item.attrs.iter().any(|attr| {
+        attr.path().is_ident("derive")
+            && attr
+                .meta
+                .to_token_stream()
+                .to_string()
+                .contains("Deserialize")
+    });
+    if !derives_deserialize || has_serde_flag(&item.attrs, "transparent") {
+        return None;
+    }
+
+    let syn::Fields::Named(fields) = &mut item.fields else {
+        return None;
+    };
+    if fields
+        .named
+        .iter()
+        .any(|field| has_serde_flag(&field.attrs, "flatten"))
+    {
+        return None;
+    }
+    let name = EXTRA_FIELDS.iter().find(|name| {
+        !fields
+            .named
+            .iter()
+            .any(|field| field.ident.as_ref().is_some_and(|ident| ident == *name))
+    })?;
+    let name = format_ident!("{}", name);
+
+    fields.named.push(syn::parse_quote! {
+        /// Fields returned by the API that the schema does not declare.
+        #[serde(flatten)]
+        pub #name: ::serde_json::Map<::std::string::String, ::serde_json::Value>
+    });
+    // `additionalProperties: false` is not trusted either
+    remove_serde_flag(&mut item.attrs, "deny_unknown_fields");
+
+    Some(name)
+}
+
+/// Whether a `#[serde(...)]` attribute in `attrs` contains `flag`.
+fn has_serde_flag(attrs: &[syn::Attribute], flag: &str) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"))
+        .filter_map(|attr| serde_args(attr).ok())
+        .flatten()
+        .any(|meta| meta.path().is_ident(flag))
+}
+
+fn remove_serde_flag(attrs: &mut Vec<syn::Attribute>, flag: &str) {
+    attrs.retain_mut(|attr| {
+        if !attr.path().is_ident("serde") {
+            return true;
+        }
+        let Ok(args) = serde_args(attr) else {
+            return true;
+        };
+        let args: Vec<syn::Meta> = args
+            .into_iter()
+            .filter(|meta| !meta.path().is_ident(flag))
+            .collect();
+        if args.is_empty() {
+            return false;
+        }
+        *attr = syn::parse_quote!(#[serde(#(#args),*)]);
+        true
+    });
+}
+
+fn serde_args(
+    attr: &syn::Attribute,
+) -> syn::Result<syn::punctuated::Punctuated<syn::Meta, syn::Token![,]>> {
+    attr.parse_args_with(syn::punctuated::Punctuated::parse_terminated)
+}
+
+/// The identifiers of a plain type path such as `super::Zone`.
+fn path_idents(ty: &syn::Type) -> Vec<String> {
+    match ty {
+        syn::Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Completes the struct literals of one changed struct in an impl of it.
+struct Literals {
+    name: String,
+    /// Whether the impl is in the child module, naming it `super::Name`.
+    in_parent: bool,
+    field: syn::Ident,
+}
+
+impl VisitMut for Literals {
+    fn visit_expr_struct_mut(&mut self, expr: &mut syn::ExprStruct) {
+        visit_mut::visit_expr_struct_mut(self, expr);
+
+        let path: Vec<String> = expr
+            .path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect();
+        let names_struct = match path.as_slice() {
+            [name] => name == "Self" || (!self.in_parent && *name == self.name),
+            [module, name] => self.in_parent && module == "super" && *name == self.name,
+            _ => false,
+        };
+        let complete = expr.rest.is_some()
+            || expr.fields.iter().any(
+                |field| matches!(&field.member, syn::Member::Named(ident) if *ident == self.field),
+            );
+        if !names_struct || complete {
+            return;
+        }
+
+        let field = &self.field;
+        expr.fields
+            .push(syn::parse_quote!(#field: ::std::default::Default::default()));
+    }
+}
@@ -0,0 +1,92 @@
+//! Request bodies Progenitor can send.
+//!
+//! Progenitor sends a request body as JSON, form data
+//! (`application/x-www-form-urlencoded`), raw bytes
+//! (`application/octet-stream`) or text, and fails on a body that offers
+//! any other media type or more than one. Every request body, inline or under
+//! `components.requestBodies`, keeps only its first supported media type in
+//! that order. A body with none of them, such as `multipart/form-data`, is
+//! left as it is: sending it as anything else would break the endpoint, so
+//! an endpoint that takes another form of body gets it from an overlay, like
+//! the raw Workers KV value write.
+//!
+//! Each change is recorded in the diagnostics report.
+
+use serde_json::Value;
+
+use crate::diagnostics::{Diagnostics, Kind};
+use crate::filter::is_operation_method;
+
+/// Media types Progenitor sends, in order of preference.
+const SUPPORTED: &[&str] = &[
+    "application/json",
+    "application/x-www-form-urlencoded",
+    "application/octet-stream",
+    "text/plain",
+];
+
+/// Reduce every request body of `spec` to one media type Progenitor sends.
+pub fn supported_request_bodies(spec: &mut Value, diagnostics: &mut Diagnostics) {
+    if let Some(bodies) = spec
+        .pointer_mut("/components/requestBodies")
+        .and_then(|b| b.as_object_mut())
+    {
+        for (name, body) in bodies.iter_mut() {
+            let location = format!("requestBodies/{}", name);
+            reduce(body, &location, diagnostics);
+        }
+    }
+
+    let Some(paths) = spec.get_mut("paths").and_then(|p| p.as_object_mut()) else {
+        return;
+    };
+    for (path, item) in paths.iter_mut() {
+        let Some(item) = item.as_object_mut() else {
+            continue;
+        };
+        for (method, operation) in item.iter_mut() {
+            if !is_operation_method(method) {
+                continue;
+            }
+            if let Some(body) = operation.get_mut("requestBody") {
+                let location = format!("{} {} requestBody", method.to_uppercase(), path);
+                reduce(body, &location, diagnostics);
+            }
+        }
+    }
+}
+
+fn reduce(body: &mut Value, location: &str, diagnostics: &mut Diagnostics) {
+    let Some(content) = body.get_mut("content").and_then(|c| c.as_object_mut()) else {
+        return;
+    };
+    let types: Vec<String> = content.keys().cloned().collect();
+    if types.is_empty() {
+        return;
+    }
+    if let [only] = types.as_slice()
+        && SUPPORTED.contains(&essence(only))
+    {
+        return;
+    }
+
+    let Some(kept) = SUPPORTED
+        .iter()
+        .find_map(|supported| types.iter().find(|ty| essence(ty) == *supported))
+        .cloned()
+    else {
+        return;
+    };
+    content.retain(|ty, _| *ty == kept);
+    diagnostics.push(
+        Kind::MediaTypeChanged,
+        location,
+        format!("kept {} of {}", kept, types.join(", ")),
+    );
+}
+
+/// A media type without its parameters, e.g. `application/json` for
+/// `application/json; charset=utf-8`.
+fn essence(media_type: &str) -> &str {
+    media_type.split(';').next().unwrap_or_default().trim()
+}
@@ -0,0 +1,210 @@
+//! Canned responses for the mock server (`mock` feature).
+//!
+//! Every operation of the patched spec gets the body of its first success
+//! response: the media type `example` if there is one, otherwise a value
+//! synthesized from the schema (`example`, `default` or the first `enum`
+//! value of each field, else a placeholder for its type). Bodies are always
+//! wrapped in the Cloudflare envelope.
+
+use serde_json::{Map, Value, json};
+
+use crate::filter::is_operation_method;
+
+/// How deep to synthesize nested objects and arrays.
+const MAX_DEPTH: usize = 12;
+
+/// Render `MOCK_ROUTES`: `(method, path template, status, body)` for every operation.
+pub fn mock_routes(spec: &Value) -> String {
+    let mut routes = Vec::new();
+
+    if let Some(paths) = spec.get("paths").and_then(|p| p.as_object()) {
+        for (path, item) in paths {
+            let Some(operations) = item.as_object() else {
+                continue;
+            };
+            for (method, operation) in operations {
+                if !is_operation_method(method) {
+                    continue;
+                }
+                let (status, body) = canned_response(spec, operation);
+                routes.push((method.to_uppercase(), path.clone(), status, body));
+            }
+        }
+    }
+
+    routes.sort();
+
+    let mut code = String::from(
+        "/// Canned responses derived from the spec: method, path template, status and body.\n\
+         pub(crate) const MOCK_ROUTES: &[(&str, &str, u16, &str)] = &[\n",
+    );
+    for (method, path, status, body) in routes {
+        code.push_str(&format!(
+            "    ({:?}, {:?}, {}, {:?}),\n",
+            method, path, status, body
+        ));
+    }
+    code.push_str("];\n");
+
+    code
+}
+
+/// Status and body of the first success response of an operation.
+fn canned_response(spec: &Value, operation: &Value) -> (u16, String) {
+    let success = operation
+        .get("responses")
+        .and_then(|r| r.as_object())
+        .and_then(|responses| {
+            responses
+                .iter()
+                .filter_map(|(status, response)| Some((status.parse::<u16>().ok()?, response)))
+                .filter(|(status, _)| (200..300).contains(status))
+                .min_by_key(|(status, _)| *status)
+        });
+
+    let Some((status, response)) = success else {
+        return (200, envelope(Value::Null).to_string());
+    };
+    let response = resolve(spec, response);
+
+    let Some(media) = response
+        .get("content")
+        .and_then(|c| c.get("application/json"))
+    else {
+        return (status, String::new());
+    };
+
+    let value = media
+        .get("example")
+        .cloned()
+        .or_else(|| {
+            media
+                .get("examples")
+                .and_then(|e| e.as_object())
+                .and_then(|examples| examples.values().next())
+                .map(|example| resolve(spec, example))
+                .and_then(|example| example.get("value").cloned())
+        })
+        .unwrap_or_else(|| match media.get("schema") {
+            Some(schema) => synthesize(spec, schema, &mut Vec::new(), 0),
+            None => Value::Null,
+        });
+
+    (status, envelope(value).to_string())
+}
+
+/// Wrap a body in the Cloudflare envelope, unless it already is one.
+fn envelope(value: Value) -> Value {
+    match value {
+        Value::Object(mut object) if object.contains_key("success") => {
+            object.insert("success".to_string(), Value::Bool(true));
+            object.insert("errors".to_string(), json!([]));
+            object.entry("messages").or_insert_with(|| json!([]));
+            object.entry("result").or_insert(Value::Null);
+            Value::Object(object)
+        }
+        result => json!({
+            "success": true,
+            "errors": [],
+            "messages": [],
+            "result": result,
+        }),
+    }
+}
+
+/// Follow a local `$ref`, returning the value itself otherwise.
+fn resolve<'a>(spec: &'a Value, value: &'a Value) -> &'a Value {
+    match value.get("$ref").and_then(|r| r.as_str()) {
+        Some(reference) => reference
+            .strip_prefix('#')
+            .and_then(|pointer| spec.pointer(pointer))
+            .unwrap_or(value),
+        None => value,
+    }
+}
+
+/// A value matching `schema`. `visiting` holds the `$ref`s being expanded.
+fn synthesize(spec: &Value, schema: &Value, visiting: &mut Vec<String>, depth: usize) -> Value {
+    if let Some(reference) = schema.get("$ref").and_then(|r| r.as_str()) {
+        if visiting.iter().any(|r| r == reference) {
+            return Value::Null;
+        }
+        visiting.push(reference.to_string());
+        let value = synthesize(spec, resolve(spec, schema), visiting, depth);
+        visiting.pop();
+        return value;
+    }
+
+    if let Some(value) = ["example", "default"]
+        .iter()
+        .find_map(|keyword| schema.get(*keyword))
+        .or_else(|| {
+            schema
+                .get("enum")
+                .and_then(|e| e.as_array())
+                .and_then(|e| e.first())
+        })
+    {
+        return value.clone();
+    }
+
+    for keyword in ["oneOf", "anyOf", "allOf"] {
+        if let Some(first) = schema
+            .get(keyword)
+            .and_then(|m| m.as_array())
+            .and_then(|m| m.first())
+        {
+            return synthesize(spec, first, visiting, depth);
+        }
+    }
+
+    let kind = schema
+        .get("type")
+        .and_then(|t| t.as_str())
+        .unwrap_or_else(|| {
+            if schema.get("properties").is_some() {
+                "object"
+            } else if schema.get("items").is_some() {
+                "array"
+            } else {
+                ""
+            }
+        });
+
+    match kind {
+        "object" => {
+            let mut object = Map::new();
+            if depth < MAX_DEPTH
+                && let Some(properties) = schema.get("properties").and_then(|p| p.as_object())
+            {
+                for (name, property) in properties {
+                    let value = synthesize(spec, property, visiting, depth + 1);
+                    object.insert(name.clone(), value);
+                }
+            }
+            Value::Object(object)
+        }
+        "array" => match schema.get("items") {
+            Some(items) if depth < MAX_DEPTH => {
+                json!([synthesize(spec, items, visiting, depth + 1)])
+            }
+            _ => json!([]),
+        },
+        "string" => Value::String(
+            match schema.get("format").and_then(|f| f.as_str()) {
+                Some("date-time") => "2014-01-01T05:20:00.12345Z",
+                Some("date") => "2014-01-01",
+                Some("uuid") => "f174e90a-fafe-4643-bbbc-4a0ed4fc8415",
+                Some("email") => "user@example.com",
+                Some("uri" | "url") => "https://example.com",
+                Some("ipv4") => "192.0.2.1",
+                Some("ipv6") => "2001:db8::1",
+                _ => "string",
+            }
+            .to_string(),
+        ),
+        "integer" | "number" => schema.get("minimum").cloned().unwrap_or(json!(0)),
+        "boolean" => Value::Bool(true),
+        _ => Value::Null,
+    }
+}
@@ -0,0 +1,366 @@
+//! Split the generated client into a module tree keyed by product area.
+//!
+//! Every OpenAPI tag is assigned to a module such as `dns` or
+//! `zero_trust::access`. The module re-exports the client extension trait
+//! that Progenitor generates for each of its tags, and owns the component
+//! schemas only its operations use. Schemas used by several modules live in
+//! `common`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use heck::{ToPascalCase, ToSnakeCase};
+use serde_json::Value;
+
+use crate::filter::{collect_refs, parse_component_ref};
+
+/// Module for the schemas shared between modules.
+pub const COMMON: &str = "common";
+
+/// Tag prefixes and the module their operations belong to. The first match
+/// wins, so more specific prefixes come first.
+const TAG_MODULES: &[(&str, &str)] = &[
+    ("Account", "accounts"),
+    ("DNS", "dns"),
+    ("Workers KV", "kv"),
+    ("Worker", "workers"),
+    ("R2", "r2"),
+    ("Zone", "zones"),
+    ("Access", "zero_trust::access"),
+    ("Zero Trust Gateway", "zero_trust::gateway"),
+    ("Gateway", "zero_trust::gateway"),
+    ("Devices", "zero_trust::devices"),
+    ("Device", "zero_trust::devices"),
+    ("Cloudflare Tunnel", "zero_trust::tunnels"),
+    ("Tunnel", "zero_trust::tunnels"),
+    ("DLP", "zero_trust::dlp"),
+    ("Zero Trust", "zero_trust"),
+];
+
+/// Names that are already taken at the crate root.
+const RESERVED: &[&str] = &["builder", "prelude", "types", "hooks", COMMON];
+
+/// Where every tag and component schema of the spec is generated.
+pub struct ModuleTree {
+    /// Module path for each tag.
+    tag_modules: BTreeMap<String, String>,
+    /// Module path for each component schema.
+    schema_modules: BTreeMap<String, String>,
+}
+
+impl ModuleTree {
+    /// Assign tags to modules, and schemas to the module that uses them.
+    pub fn plan(spec: &Value) -> Self {
+        let mut tag_modules = BTreeMap::new();
+        // Schemas directly referenced by each module's operations
+        let mut module_refs: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+        for (path, operation) in operations(spec) {
+            let tags: Vec<&str> = operation
+                .get("tags")
+                .and_then(|t| t.as_array())
+                .map(|tags| tags.iter().filter_map(|t| t.as_str()).collect())
+                .unwrap_or_default();
+
+            for tag in tags {
+                let module = tag_modules
+                    .entry(tag.to_string())
+                    .or_insert_with(|| module_for(tag, path))
+                    .clone();
+                collect_refs(operation, module_refs.entry(module).or_default());
+            }
+        }
+
+        // Which modules reach each schema, following references transitively
+        let mut users: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for (module, refs) in module_refs {
+            let mut pending = refs;
+            let mut seen = BTreeSet::new();
+
+            while let Some(reference) = pending.pop() {
+                let Some((kind, name)) = parse_component_ref(&reference) else {
+                    continue;
+                };
+                if !seen.insert((kind.clone(), name.clone())) {
+                    continue;
+                }
+                if kind == "schemas" {
+                    users
+                        .entry(name.clone())
+                        .or_default()
+                        .insert(module.clone());
+                }
+
+                let component = spec
+                    .get("components")
+                    .and_then(|c| c.get(&kind))
+                    .and_then(|k| k.get(&name));
+                if let Some(component) = component {
+                    collect_refs(component, &mut pending);
+                }
+            }
+        }
+
+        let mut schema_modules = BTreeMap::new();
+        if let Some(schemas) = spec
+            .pointer("/components/schemas")
+            .and_then(|s| s.as_object())
+        {
+            for name in schemas.keys() {
+                let module = match users.get(name) {
+                    Some(modules) if modules.len() == 1 => modules.iter().next().cloned(),
+                    _ => None,
+                };
+                schema_modules.insert(name.clone(), module.unwrap_or_else(|| COMMON.to_string()));
+            }
+        }
+
+        ModuleTree {
+            tag_modules,
+            schema_modules,
+        }
+    }
+
+    /// Every module that owns at least one schema.
+    pub fn type_modules(&self) -> BTreeSet<&str> {
+        self.schema_modules.values().map(String::as_str).collect()
+    }
+
+    /// Path of the file holding a module's types, relative to `OUT_DIR`.
+    pub fn types_file(module: &str) -> String {
+        format!("modules/{}.rs", module.replace("::", "/"))
+    }
+
+    /// A copy of `spec` for generating `module`'s types: without operations,
+    /// and with only the schemas `module` owns and those they reference,
+    /// transitively, so references resolve. Those owned by other modules are
+    /// replaced, see [`ModuleTree::replacements`].
+    pub fn types_spec(&self, spec: &Value, module: &str) -> Value {
+        let schemas = spec.pointer("/components/schemas");
+        let mut pending: Vec<String> = self
+            .schema_modules
+            .iter()
+            .filter(|(_, owner)| *owner == module)
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        let mut kept = serde_json::Map::new();
+        while let Some(name) = pending.pop() {
+            if kept.contains_key(&name) {
+                continue;
+            }
+            let Some(schema) = schemas.and_then(|s| s.get(&name)) else {
+                continue;
+            };
+
+            let mut refs = Vec::new();
+            collect_refs(schema, &mut refs);
+            pending.extend(
+                refs.iter()
+                    .filter_map(|reference| parse_component_ref(reference))
+                    .filter(|(kind, _)| kind == "schemas")
+                    .map(|(_, name)| name),
+            );
+            kept.insert(name, schema.clone());
+        }
+
+        serde_json::json!({
+            "openapi": spec["openapi"],
+            "info": spec["info"],
+            "paths": {},
+            "components": { "schemas": kept },
+        })
+    }
+
+    /// Rust type replacements for every schema not owned by `module`, as
+    /// `(generated type name, replacement path)`. With `None`, every schema
+    /// is replaced.
+    pub fn replacements(&self, module: Option<&str>) -> Vec<(String, String)> {
+        self.schema_modules
+            .iter()
+            .filter(|(_, owner)| Some(owner.as_str()) != module)
+            .map(|(name, owner)| (type_name(name), type_path(owner, name)))
+            .collect()
+    }
+
+    /// Path of the Rust type generated for a component schema, e.g.
+    /// `crate::dns::types::DnsRecordsRecord`.
+    pub fn type_path(&self, schema: &str) -> Option<String> {
+        let owner = self.schema_modules.get(schema)?;
+        Some(type_path(owner, schema))
+    }
+
+    /// Render `modules.rs`, declaring the module tree.
+    pub fn render(&self) -> String {
+        let mut root = Node::default();
+
+        for (tag, module) in &self.tag_modules {
+            root.child(module).tags.insert(tag.clone());
+        }
+        for module in self.type_modules() {
+            root.child(module).types = Some(Self::types_file(module));
+        }
+
+        let mut code = String::new();
+        root.render_children(&mut code, 0);
+        code
+    }
+}
+
+#[derive(Default)]
+struct Node {
+    tags: BTreeSet<String>,
+    types: Option<String>,
+    children: BTreeMap<String, Node>,
+}
+
+impl Node {
+    fn child(&mut self, path: &str) -> &mut Node {
+        path.split("::").fold(self, |node, name| {
+            node.children.entry(name.to_string()).or_default()
+        })
+    }
+
+    fn render_children(&self, code: &mut String, depth: usize) {
+        let indent = "    ".repeat(depth);
+
+        for (name, node) in &self.children {
+            if node.tags.is_empty() {
+                code.push_str(&format!(
+                    "{}/// Types shared between API modules.\n",
+                    indent
+                ));
+            } else {
+                let tags: Vec<&str> = node.tags.iter().map(String::as_str).collect();
+                code.push_str(&format!(
+                    "{}/// Operations tagged {}.\n",
+                    indent,
+                    tags.join(", ")
+                ));
+            }
+            code.push_str(&format!("{}pub mod {} {{\n", indent, name));
+
+            let inner = "    ".repeat(depth + 1);
+            for tag in &node.tags {
+                code.push_str(&format!("{}pub use crate::{};\n", inner, trait_name(tag)));
+            }
+            if let Some(types) = &node.types {
+                code.push_str(&format!(
+                    "{}/// Types used by this module's operations.\n\
+                     {}#[allow(clippy::all)]\n\
+                     {}pub mod types {{\n\
+                     {}    include!(concat!(env!(\"OUT_DIR\"), \"/{}\"));\n\
+                     {}}}\n",
+                    inner, inner, inner, inner, types, inner
+                ));
+            }
+            node.render_children(code, depth + 1);
+
+            code.push_str(&format!("{}}}\n", indent));
+        }
+    }
+}
+
+/// Extract the items of the `types` module from a Progenitor run.
+pub fn extract_types(tokens: proc_macro2::TokenStream) -> Result<String, syn::Error> {
+    let file: syn::File = syn::parse2(tokens)?;
+
+    let items = file
+        .items
+        .into_iter()
+        .find_map(|item| match item {
+            syn::Item::Mod(module) if module.ident == "types" => module.content,
+            _ => None,
+        })
+        .map(|(_, items)| items)
+        .unwrap_or_default();
+
+    Ok(items
+        .iter()
+        .map(|item| quote::quote!(#item).to_string())
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Path of a schema's type in the module that owns it.
+fn type_path(module: &str, schema: &str) -> String {
+    format!("crate::{}::types::{}", module, type_name(schema))
+}
+
+/// The Rust type typify generates for a component schema.
+pub fn type_name(schema: &str) -> String {
+    sanitize(schema, |s| s.to_pascal_case())
+}
+
+/// The client extension trait Progenitor generates for a tag.
+pub fn trait_name(tag: &str) -> String {
+    format!("Client{}Ext", sanitize(tag, |s| s.to_pascal_case()))
+}
+
+/// Turn a name into an identifier the way typify and Progenitor do.
+fn sanitize(name: &str, to_case: impl Fn(&str) -> String) -> String {
+    let name: String = name
+        .replace('\'', "")
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '-' })
+        .collect();
+    let name = to_case(&name);
+
+    let name = match name.chars().next() {
+        None => to_case("x"),
+        Some(c) if c.is_alphabetic() || c == '_' => name,
+        Some(_) => format!("_{}", name),
+    };
+
+    if syn::parse_str::<syn::Ident>(&name).is_ok() {
+        name
+    } else {
+        format!("{}_", name)
+    }
+}
+
+/// Every `(path, operation)` of the spec.
+fn operations(spec: &Value) -> Vec<(&str, &Value)> {
+    let Some(paths) = spec.get("paths").and_then(|p| p.as_object()) else {
+        return Vec::new();
+    };
+
+    paths
+        .iter()
+        .filter_map(|(path, item)| Some((path, item.as_object()?)))
+        .flat_map(|(path, item)| {
+            item.iter()
+                .filter(|(method, _)| {
+                    [
+                        "get", "put", "post", "delete", "options", "head", "patch", "trace",
+                    ]
+                    .contains(&method.as_str())
+                })
+                .map(move |(_, operation)| (path.as_str(), operation))
+        })
+        .collect()
+}
+
+/// Module for a tag, from the product table or else from the first resource
+/// in the path of an operation with that tag, e.g. `images` for
+/// `/accounts/{account_id}/images/v1`.
+fn module_for(tag: &str, path: &str) -> String {
+    if let Some((_, module)) = TAG_MODULES
+        .iter()
+        .find(|(prefix, _)| tag.starts_with(prefix))
+    {
+        return module.to_string();
+    }
+
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let resource = match segments.as_slice() {
+        ["accounts" | "zones", id, resource, ..] if id.starts_with('{') => resource,
+        [first, ..] => first,
+        [] => "api",
+    };
+
+    let mut module = sanitize(resource, |s| s.to_snake_case());
+    if RESERVED.contains(&module.as_str()) {
+        module.push_str("_api");
+    }
+    module
+}
@@ -0,0 +1,347 @@
+//! Readable, stable names for the generated client methods.
+//!
+//! Every operation is renamed to `<resource>_<verb>[_<sub-resource>]`, e.g.
+//! `GET /accounts/{account_id}/workers/scripts/{script_name}/content/v2`
+//! becomes `workers_scripts_get_content`. Names are recorded in
+//! `schema/operation_names.json`, keyed by `METHOD /path`, and a recorded name
+//! always wins so methods keep their names when Cloudflare reorders or
+//! renames things upstream. Edit the file to rename a method; entries of
+//! operations that are gone are only ever removed by hand.
+//!
+//! The original `operationId` is kept as `x-original-operation-id` and, with
+//! the raw path, becomes a `#[doc(alias)]` on the generated method.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use heck::ToSnakeCase;
+use serde_json::Value;
+
+use crate::diagnostics::{Diagnostics, Kind};
+use crate::filter::is_operation_method;
+
+/// Checked-in operation names, relative to the crate root.
+pub const NAMES_PATH: &str = "schema/operation_names.json";
+
+/// Extension holding the upstream `operationId`.
+const ORIGINAL_ID: &str = "x-original-operation-id";
+
+/// Path segments that scope a resource rather than name it.
+const SCOPES: &[&str] = &["accounts", "zones"];
+
+/// Method names and the search aliases of each.
+pub struct OperationNames {
+    /// `METHOD /path` to method name, for every operation in the spec.
+    pub names: BTreeMap<String, String>,
+    /// Operations that were not in the checked-in file.
+    pub added: usize,
+    /// Checked-in entries, `(METHOD /path, name)`, for operations no longer
+    /// in the spec.
+    pub removed: Vec<(String, String)>,
+    /// Method name to its `#[doc(alias)]`es.
+    aliases: BTreeMap<String, Vec<String>>,
+}
+
+/// Rename every operation of `spec`, preferring the names recorded in `names_path`.
+///
+/// The file may only be missing when `update` is set, i.e. when the names
+/// are about to be recorded; otherwise every method would silently fall back
+/// to a derived name.
+pub fn assign_operation_names(
+    spec: &mut Value,
+    names_path: &Path,
+    update: bool,
+    diagnostics: &mut Diagnostics,
+) -> Result<OperationNames, Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed={}", names_path.display());
+    let recorded = read_names(names_path, update)?;
+
+    let Some(paths) = spec.get_mut("paths").and_then(|p| p.as_object_mut()) else {
+        return Ok(OperationNames {
+            names: BTreeMap::new(),
+            added: 0,
+            removed: recorded.into_iter().collect(),
+            aliases: BTreeMap::new(),
+        });
+    };
+
+    // Every operation, in a deterministic order
+    let mut operations = Vec::new();
+    for (path, item) in paths.iter() {
+        let Some(item) = item.as_object() else {
+            continue;
+        };
+        for method in item.keys().filter(|m| is_operation_method(m)) {
+            operations.push((
+                format!("{} {}", method.to_uppercase(), path),
+                method.clone(),
+                path.clone(),
+            ));
+        }
+    }
+    operations.sort();
+
+    let mut names = BTreeMap::new();
+    let mut taken = BTreeSet::new();
+    for (key, _, _) in &operations {
+        if let Some(name) = recorded.get(key) {
+            names.insert(key.clone(), name.clone());
+            taken.insert(name.clone());
+        }
+    }
+
+    // Name new operations, qualifying names that would collide
+    let unnamed: Vec<_> = operations
+        .iter()
+        .filter(|(key, _, _)| !names.contains_key(key))
+        .collect();
+    let mut base_counts = BTreeMap::new();
+    for (_, method, path) in &unnamed {
+        *base_counts
+            .entry(readable_name(method, path, false))
+            .or_insert(0) += 1;
+    }
+    for (key, method, path) in &unnamed {
+        let base = readable_name(method, path, false);
+        let mut name = if base_counts[&base] > 1 || taken.contains(&base) {
+            readable_name(method, path, true)
+        } else {
+            base
+        };
+        if taken.contains(&name) {
+            let mut n = 2;
+            while taken.contains(&format!("{}_{}", name, n)) {
+                n += 1;
+            }
+            diagnostics.push(
+                Kind::OperationNameCollision,
+                key.as_str(),
+                format!("`{}` is taken, named `{}_{}`", name, name, n),
+            );
+            name = format!("{}_{}", name, n);
+        }
+        taken.insert(name.clone());
+        names.insert(key.clone(), name);
+    }
+
+    // Rename the operations, keeping the upstream id for the docs
+    let mut aliases = BTreeMap::new();
+    for (key, method, path) in &operations {
+        let name = &names[key];
+        let Some(operation) = paths
+            .get_mut(path)
+            .and_then(|item| item.get_mut(method))
+            .and_then(|op| op.as_object_mut())
+        else {
+            continue;
+        };
+
+        let mut method_aliases = vec![path.clone()];
+        match operation.get("operationId").and_then(|id| id.as_str()) {
+            Some(original) => {
+                method_aliases.push(original.to_string());
+                operation.insert(ORIGINAL_ID.to_string(), Value::String(original.to_string()));
+            }
+            None => diagnostics.push(
+                Kind::MissingOperationId,
+                key.as_str(),
+                format!("named `{}`", name),
+            ),
+        }
+        operation.insert("operationId".to_string(), Value::String(name.clone()));
+        aliases.insert(name.clone(), method_aliases);
+    }
+
+    let removed = recorded
+        .into_iter()
+        .filter(|(key, _)| !names.contains_key(key))
+        .collect();
+
+    Ok(OperationNames {
+        names,
+        added: unnamed.len(),
+        removed,
+        aliases,
+    })
+}
+
+fn read_names(
+    path: &Path,
+    update: bool,
+) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+    if !path.exists() {
+        if update {
+            return Ok(BTreeMap::new());
+        }
+        return Err(format!(
+            "{} is missing; set CLOUDFLARE_API_UPDATE_OPERATION_NAMES=1 to record the names",
+            path.display()
+        )
+        .into());
+    }
+
+    let content = fs::read_to_string(path)?;
+    let names: BTreeMap<String, String> = serde_json::from_str(&content)
+        .map_err(|e| format!("{}: expected an object of names: {}", path.display(), e))?;
+
+    let mut seen = BTreeMap::new();
+    for (key, name) in &names {
+        if name.to_snake_case() != *name || syn::parse_str::<syn::Ident>(name).is_err() {
+            return Err(format!(
+                "{}: `{}` for {} is not a snake_case identifier",
+                path.display(),
+                name,
+                key
+            )
+            .into());
+        }
+        if let Some(other) = seen.insert(name, key) {
+            return Err(format!(
+                "{}: `{}` is used for both {} and {}",
+                path.display(),
+                name,
+                other,
+                key
+            )
+            .into());
+        }
+    }
+    Ok(names)
+}
+
+/// `<resource>_<verb>[_<sub-resource>]` for an operation. `qualified` keeps
+/// the account or zone scope, e.g. `zone_rulesets_list`.
+fn readable_name(method: &str, path: &str, qualified: bool) -> String {
+    let mut segments: Vec<&str> = path
+        .split('/')
+        .filter(|s| !s.is_empty() && !is_version(s))
+        .collect();
+
+    let mut words = Vec::new();
+    if let [scope, id, _, ..] = segments.as_slice()
+        && SCOPES.contains(scope)
+        && id.starts_with('{')
+    {
+        if qualified {
+            words.push(scope.trim_end_matches('s').to_string());
+        }
+        segments.drain(..2);
+    }
+
+    // Resource words come before the last parameter, sub-resource words after
+    let last_param = segments.iter().rposition(|s| s.starts_with('{'));
+    let (resource, sub_resource) = match last_param {
+        Some(index) => (&segments[..index], &segments[index + 1..]),
+        None => (&segments[..], &[][..]),
+    };
+    let on_item = last_param.is_some();
+
+    words.extend(
+        resource
+            .iter()
+            .filter(|s| !s.starts_with('{'))
+            .map(|s| s.to_snake_case()),
+    );
+    words.push(
+        match method {
+            "get" if on_item => "get",
+            "get" => "list",
+            "post" => "create",
+            "put" => "update",
+            "patch" => "edit",
+            other => other,
+        }
+        .to_string(),
+    );
+    words.extend(sub_resource.iter().map(|s| s.to_snake_case()));
+
+    let name = words
+        .into_iter()
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join("_");
+    match name.chars().next() {
+        Some(c) if c.is_ascii_digit() => format!("op_{}", name),
+        _ => name,
+    }
+}
+
+/// Whether a path segment is an API version such as `v2`.
+fn is_version(segment: &str) -> bool {
+    segment
+        .strip_prefix('v')
+        .is_some_and(|rest| !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit()))
+}
+
+impl OperationNames {
+    /// The names as written to [`NAMES_PATH`].
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(&self.names).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+
+    /// Add `#[doc(alias)]`es for the raw path and upstream `operationId` to
+    /// the generated methods.
+    ///
+    /// Aliases go on the trait declarations and inherent methods, the only
+    /// places rustdoc accepts them.
+    pub fn add_doc_aliases(
+        &self,
+        tokens: proc_macro2::TokenStream,
+    ) -> Result<proc_macro2::TokenStream, syn::Error> {
+        let mut file: syn::File = syn::parse2(tokens)?;
+        self.alias_items(&mut file.items);
+        Ok(quote::quote!(#file))
+    }
+
+    fn alias_items(&self, items: &mut [syn::Item]) {
+        for item in items {
+            match item {
+                syn::Item::Trait(item) => {
+                    for item in &mut item.items {
+                        if let syn::TraitItem::Fn(function) = item {
+                            self.alias(&function.sig.ident, &mut function.attrs);
+                        }
+                    }
+                }
+                syn::Item::Impl(item) if item.trait_.is_none() && is_client(&item.self_ty) => {
+                    for item in &mut item.items {
+                        if let syn::ImplItem::Fn(function) = item {
+                            self.alias(&function.sig.ident, &mut function.attrs);
+                        }
+                    }
+                }
+                syn::Item::Mod(module) => {
+                    if let Some((_, items)) = &mut module.content {
+                        self.alias_items(items);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn alias(&self, ident: &syn::Ident, attrs: &mut Vec<syn::Attribute>) {
+        let name = ident.to_string();
+        let Some(aliases) = self.aliases.get(&name) else {
+            return;
+        };
+        // rustdoc rejects quotes, surrounding spaces and the item's own name
+        let aliases: BTreeSet<&String> = aliases
+            .iter()
+            .filter(|alias| !alias.contains(['"', '\'']) && alias.trim() == alias.as_str())
+            .filter(|alias| **alias != name)
+            .collect();
+        attrs.extend(
+            aliases
+                .into_iter()
+                .map(|alias| syn::parse_quote!(#[doc(alias = #alias)])),
+        );
+    }
+}
+
+fn is_client(ty: &syn::Type) -> bool {
+    matches!(ty, syn::Type::Path(path) if path.path.is_ident("Client"))
+}
@@ -0,0 +1,346 @@
+//! Targeted fixes for individual schemas, applied before the generic
+//! simplifications.
+//!
+//! Every `.json` file in `overlays/` is applied to the parsed spec in file
+//! name order. A file is either:
+//!
+//! - an RFC 6902 JSON Patch: an array of `add`, `remove`, `replace`, `move`,
+//!   `copy` and `test` operations addressed by JSON Pointer, or
+//! - an OpenAPI Overlay 1.0 document: an object with `overlay` and `actions`,
+//!   where each action has a JSONPath `target` and either `update` or
+//!   `remove: true`.
+//!
+//! Overlay targets support `$`, `.name`, `['name']`, `[n]`, `.*` and `[*]`.
+//! Unlike the Overlay specification, a target that matches nothing is an
+//! error, so a fix that no longer applies upstream is noticed instead of
+//! silently ignored.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::diagnostics::{Diagnostics, Kind};
+
+/// Overlay directory, relative to the crate root.
+pub const OVERLAY_DIR: &str = "overlays";
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Apply every overlay file in `dir` to `spec`, in file name order.
+pub fn apply_overlays(spec: &mut Value, dir: &Path, diagnostics: &mut Diagnostics) -> Result<()> {
+    println!("cargo:rerun-if-changed={}", dir.display());
+    for path in overlay_files(dir)? {
+        println!("cargo:rerun-if-changed={}", path.display());
+        let file = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let content = fs::read_to_string(&path)?;
+        let document: Value = serde_json::from_str(&content)
+            .map_err(|e| format!("overlay {}: invalid JSON: {}", file, e))?;
+        let applied = match &document {
+            Value::Array(operations) => apply_json_patch(spec, operations),
+            Value::Object(object) if object.contains_key("overlay") => {
+                apply_overlay(spec, &document)
+            }
+            _ => Err("expected a JSON Patch array or an Overlay document".into()),
+        }
+        .map_err(|e| format!("overlay {}: {}", file, e))?;
+        for target in applied {
+            diagnostics.push(Kind::OverlayApplied, target, format!("patched by {}", file));
+        }
+    }
+    Ok(())
+}
+
+fn overlay_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Apply a JSON Patch, returning the pointer of every modified location.
+fn apply_json_patch(spec: &mut Value, operations: &[Value]) -> Result<Vec<String>> {
+    let mut applied = Vec::new();
+    for (index, operation) in operations.iter().enumerate() {
+        apply_patch_operation(spec, operation)
+            .map_err(|e| format!("operation {}: {}", index, e))?;
+        if let Some(path) = operation.get("path").and_then(|p| p.as_str())
+            && operation.get("op").and_then(|op| op.as_str()) != Some("test")
+        {
+            applied.push(path.to_string());
+        }
+    }
+    Ok(applied)
+}
+
+fn apply_patch_operation(spec: &mut Value, operation: &Value) -> Result<()> {
+    let member = |name: &str| {
+        operation
+            .get(name)
+            .ok_or_else(|| format!("missing `{}`", name))
+    };
+    let pointer = |name: &str| -> Result<&str> {
+        member(name)?
+            .as_str()
+            .ok_or_else(|| format!("`{}` must be a JSON Pointer string", name).into())
+    };
+    let op = member("op")?.as_str().ok_or("`op` must be a string")?;
+    let path = pointer("path")?;
+
+    match op {
+        "add" => add(spec, path, member("value")?.clone()),
+        "remove" => remove(spec, path).map(drop),
+        "replace" => {
+            let target = spec
+                .pointer_mut(path)
+                .ok_or_else(|| format!("target {} does not exist", path))?;
+            *target = member("value")?.clone();
+            Ok(())
+        }
+        "move" => {
+            let value = remove(spec, pointer("from")?)?;
+            add(spec, path, value)
+        }
+        "copy" => {
+            let from = pointer("from")?;
+            let value = spec
+                .pointer(from)
+                .ok_or_else(|| format!("source {} does not exist", from))?
+                .clone();
+            add(spec, path, value)
+        }
+        "test" => {
+            let actual = spec
+                .pointer(path)
+                .ok_or_else(|| format!("target {} does not exist", path))?;
+            if actual != member("value")? {
+                return Err(format!("test failed: {} has changed upstream", path).into());
+            }
+            Ok(())
+        }
+        other => Err(format!("unknown op `{}`", other).into()),
+    }
+}
+
+/// Split a JSON Pointer into its parent pointer and unescaped last token.
+fn split_pointer(pointer: &str) -> Result<(&str, String)> {
+    let index = pointer
+        .rfind('/')
+        .ok_or_else(|| format!("{:?} is not a JSON Pointer", pointer))?;
+    let token = pointer[index + 1..].replace("~1", "/").replace("~0", "~");
+    Ok((&pointer[..index], token))
+}
+
+fn add(spec: &mut Value, pointer: &str, value: Value) -> Result<()> {
+    if pointer.is_empty() {
+        *spec = value;
+        return Ok(());
+    }
+    let (parent, token) = split_pointer(pointer)?;
+    match spec.pointer_mut(parent) {
+        Some(Value::Object(object)) => {
+            object.insert(token, value);
+            Ok(())
+        }
+        Some(Value::Array(array)) => {
+            let index = if token == "-" {
+                array.len()
+            } else {
+                token
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|index| *index <= array.len())
+                    .ok_or_else(|| format!("invalid array index in {}", pointer))?
+            };
+            array.insert(index, value);
+            Ok(())
+        }
+        Some(_) => Err(format!("parent of {} is not a container", pointer).into()),
+        None => Err(format!("parent of {} does not exist", pointer).into()),
+    }
+}
+
+fn remove(spec: &mut Value, pointer: &str) -> Result<Value> {
+    let (parent, token) = split_pointer(pointer)?;
+    let removed = match spec.pointer_mut(parent) {
+        Some(Value::Object(object)) => object.remove(&token),
+        Some(Value::Array(array)) => token
+            .parse::<usize>()
+            .ok()
+            .filter(|index| *index < array.len())
+            .map(|index| array.remove(index)),
+        _ => None,
+    };
+    removed.ok_or_else(|| format!("target {} does not exist", pointer).into())
+}
+
+/// Apply an Overlay document, returning the pointer of every modified node.
+fn apply_overlay(spec: &mut Value, document: &Value) -> Result<Vec<String>> {
+    let actions = document
+        .get("actions")
+        .and_then(|a| a.as_array())
+        .ok_or("`actions` must be an array")?;
+
+    let mut applied = Vec::new();
+    for (index, action) in actions.iter().enumerate() {
+        let target = action
+            .get("target")
+            .and_then(|t| t.as_str())
+            .ok_or_else(|| format!("action {}: `target` must be a string", index))?;
+        let pointers = select(spec, target)
+            .map_err(|e| format!("action {}: target {}: {}", index, target, e))?;
+        if pointers.is_empty() {
+            return Err(format!("action {}: target {} matches nothing", index, target).into());
+        }
+
+        if action.get("remove").and_then(|r| r.as_bool()) == Some(true) {
+            // Remove from the back so array indices stay valid
+            for pointer in pointers.iter().rev() {
+                remove(spec, pointer).map_err(|e| format!("action {}: {}", index, e))?;
+            }
+        } else if let Some(update) = action.get("update") {
+            for pointer in &pointers {
+                if let Some(node) = spec.pointer_mut(pointer) {
+                    merge(node, update);
+                }
+            }
+        } else {
+            return Err(format!("action {}: expected `update` or `remove`", index).into());
+        }
+        applied.extend(pointers);
+    }
+    Ok(applied)
+}
+
+/// Merge `update` into `target` as the Overlay specification describes:
+/// objects are merged recursively, arrays are appended and anything else is
+/// replaced.
+fn merge(target: &mut Value, update: &Value) {
+    match (target, update) {
+        (Value::Object(target), Value::Object(update)) => {
+            for (key, value) in update {
+                match target.get_mut(key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (Value::Array(target), Value::Array(update)) => target.extend(update.iter().cloned()),
+        (target, update) => *target = update.clone(),
+    }
+}
+
+/// A step of a JSONPath expression.
+enum Segment {
+    Name(String),
+    Index(usize),
+    Wildcard,
+}
+
+fn parse_path(path: &str) -> Result<Vec<Segment>> {
+    let rest = path
+        .strip_prefix('$')
+        .ok_or("JSONPath must start with `$`")?;
+    let mut segments = Vec::new();
+    let mut chars = rest.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c == '.' || c == '[' {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                }
+                segments.push(match name.as_str() {
+                    "" => return Err("recursive descent (`..`) is not supported".into()),
+                    "*" => Segment::Wildcard,
+                    _ => Segment::Name(name),
+                });
+            }
+            '[' => {
+                let mut inner = String::new();
+                let mut quote = None;
+                loop {
+                    let c = chars.next().ok_or("unterminated `[`")?;
+                    match quote {
+                        Some(q) if c == q => quote = None,
+                        Some(_) => inner.push(c),
+                        None if c == '\'' || c == '"' => {
+                            quote = Some(c);
+                            inner.push(c);
+                        }
+                        None if c == ']' => break,
+                        None => inner.push(c),
+                    }
+                }
+                segments.push(if inner == "*" {
+                    Segment::Wildcard
+                } else if let Some(name) =
+                    inner.strip_prefix('\'').or_else(|| inner.strip_prefix('"'))
+                {
+                    Segment::Name(name.to_string())
+                } else if let Ok(index) = inner.parse() {
+                    Segment::Index(index)
+                } else {
+                    return Err(format!("unsupported selector `[{}]`", inner).into());
+                });
+            }
+            other => return Err(format!("unexpected `{}`", other).into()),
+        }
+    }
+    Ok(segments)
+}
+
+/// Resolve a JSONPath expression to the JSON Pointers of the matching nodes.
+fn select(spec: &Value, path: &str) -> Result<Vec<String>> {
+    let mut matches = vec![(String::new(), spec)];
+    for segment in parse_path(path)? {
+        let mut next = Vec::new();
+        for (pointer, node) in matches {
+            let child =
+                |key: &str| format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"));
+            match (&segment, node) {
+                (Segment::Name(name), Value::Object(object)) => {
+                    if let Some(value) = object.get(name) {
+                        next.push((child(name), value));
+                    }
+                }
+                (Segment::Index(index), Value::Array(array)) => {
+                    if let Some(value) = array.get(*index) {
+                        next.push((child(&index.to_string()), value));
+                    }
+                }
+                (Segment::Wildcard, Value::Object(object)) => {
+                    next.extend(object.iter().map(|(key, value)| (child(key), value)));
+                }
+                (Segment::Wildcard, Value::Array(array)) => {
+                    next.extend(
+                        array
+                            .iter()
+                            .enumerate()
+                            .map(|(index, value)| (child(&index.to_string()), value)),
+                    );
+                }
+                _ => {}
+            }
+        }
+        matches = next;
+    }
+    Ok(matches.into_iter().map(|(pointer, _)| pointer).collect())
+}
@@ -0,0 +1,83 @@
+//! Detect which list operations are paginated, and how.
+
+use heck::ToSnakeCase;
+use serde_json::Value;
+
+/// Render `PAGINATED_OPERATIONS`, mapping generated method names to the
+/// pagination style detected from their query parameters.
+///
+/// Operations with a `cursor` parameter use cursor pagination; operations
+/// with `page` or `per_page` use page pagination.
+pub fn pagination_table(spec: &Value) -> String {
+    let mut entries = Vec::new();
+
+    if let Some(paths) = spec.get("paths").and_then(|p| p.as_object()) {
+        for path_item in paths.values() {
+            let Some(operations) = path_item.as_object() else {
+                continue;
+            };
+            let shared_params = path_item.get("parameters");
+
+            for (method, operation) in operations {
+                if method != "get" {
+                    continue;
+                }
+                let Some(operation_id) = operation.get("operationId").and_then(|o| o.as_str())
+                else {
+                    continue;
+                };
+
+                let names: Vec<String> = [operation.get("parameters"), shared_params]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|params| params.as_array())
+                    .flatten()
+                    .filter_map(|param| query_param_name(spec, param))
+                    .collect();
+
+                let style = if names.iter().any(|n| n == "cursor") {
+                    "Cursor"
+                } else if names.iter().any(|n| n == "page" || n == "per_page") {
+                    "Page"
+                } else {
+                    continue;
+                };
+
+                entries.push((operation_id.to_snake_case(), style));
+            }
+        }
+    }
+
+    entries.sort();
+
+    let mut code = String::from(
+        "/// Paginated operations and the style they use, by generated method name.\n\
+         pub const PAGINATED_OPERATIONS: &[(&str, PaginationStyle)] = &[\n",
+    );
+    for (method, style) in entries {
+        code.push_str(&format!(
+            "    ({:?}, PaginationStyle::{}),\n",
+            method, style
+        ));
+    }
+    code.push_str("];\n");
+
+    code
+}
+
+/// Name of a query parameter, following a `$ref` to `components.parameters`.
+fn query_param_name(spec: &Value, param: &Value) -> Option<String> {
+    let param = match param.get("$ref").and_then(|r| r.as_str()) {
+        Some(reference) => {
+            let name = reference.strip_prefix("#/components/parameters/")?;
+            spec.get("components")?.get("parameters")?.get(name)?
+        }
+        None => param,
+    };
+
+    if param.get("in")?.as_str()? != "query" {
+        return None;
+    }
+
+    param.get("name")?.as_str().map(str::to_string)
+}
@@ -0,0 +1,240 @@
+//! Simplify the schema shapes Progenitor cannot handle.
+//!
+//! Every schema position is visited (see `build/visit.rs`):
+//!
+//! - `allOf` compositions are merged into one schema, resolving `$ref`
+//!   members against `components.schemas`
+//! - `oneOf`/`anyOf` unions are normalised by `build/unions.rs`
+//! - constraints that contradict an `enum` are removed
+//!
+//! Lossy changes are recorded in the diagnostics report.
+
+use serde_json::{Map, Value, json};
+
+use crate::diagnostics::{Diagnostics, Kind};
+use crate::{unions, visit};
+
+/// State shared by the schema simplification passes.
+pub struct Simplifier<'a> {
+    /// `components.schemas` as found in the spec, used to resolve `$ref`s.
+    pub schemas: Map<String, Value>,
+    /// Where lossy simplifications are recorded.
+    pub diagnostics: &'a mut Diagnostics,
+}
+
+impl<'a> Simplifier<'a> {
+    pub fn new(spec: &Value, diagnostics: &'a mut Diagnostics) -> Self {
+        let schemas = spec
+            .pointer("/components/schemas")
+            .and_then(|s| s.as_object())
+            .cloned()
+            .unwrap_or_default();
+
+        Self {
+            schemas,
+            diagnostics,
+        }
+    }
+}
+
+/// Simplify `schema`, found at `location`, and the schemas nested in it.
+pub fn simplify_schema(schema: &mut Value, location: &str, ctx: &mut Simplifier) {
+    // Fix invalid schema combinations - enum with string constraints
+    if schema.get("enum").is_some()
+        && let Some(obj) = schema.as_object_mut()
+    {
+        // Remove string-specific constraints that don't make sense with enum
+        let removed: Vec<&str> = ["maxLength", "minLength", "pattern", "format"]
+            .into_iter()
+            .filter(|keyword| obj.remove(*keyword).is_some())
+            .collect();
+        if !removed.is_empty() {
+            ctx.diagnostics.push(
+                Kind::EnumConstraintRemoved,
+                location,
+                format!("removed {}", removed.join(", ")),
+            );
+        }
+        // Untyped enums get the type of their values, usually string
+        if !obj.contains_key("type") {
+            let value_type = enum_value_type(obj.get("enum"));
+            obj.insert("type".to_string(), json!(value_type));
+        }
+    }
+
+    // Handle allOf - merge all schemas into one
+    if let Some(all_of) = schema.get("allOf").and_then(|a| a.as_array()).cloned() {
+        // A lone reference is kept as a reference so the named type is reused
+        let only_annotations = schema.as_object().is_some_and(|o| {
+            o.keys()
+                .all(|k| matches!(k.as_str(), "allOf" | "description" | "title"))
+        });
+        if let [member] = all_of.as_slice()
+            && member.get("$ref").is_some()
+            && only_annotations
+        {
+            *schema = member.clone();
+            return;
+        }
+
+        let mut merged = json!({});
+        let mut visiting = Vec::new();
+
+        // Sibling keywords come first so that e.g. the composed schema's own
+        // description wins over the one inherited from its members
+        let mut siblings = schema.clone();
+        if let Some(obj) = siblings.as_object_mut() {
+            obj.remove("allOf");
+        }
+        merge_into(&mut merged, &siblings, location, ctx, &mut visiting);
+
+        // Merge all allOf items, resolving references to other schemas
+        for item in &all_of {
+            merge_into(&mut merged, item, location, ctx, &mut visiting);
+        }
+
+        let has_properties = merged
+            .get("properties")
+            .and_then(|p| p.as_object())
+            .is_some_and(|p| !p.is_empty());
+        if has_properties && merged.get("type").is_none() {
+            merged["type"] = json!("object");
+        }
+
+        // Fallback: nothing could be merged, so convert to a generic object
+        if merged.as_object().is_some_and(|o| o.is_empty()) {
+            merged = json!({"type": "object"});
+        }
+
+        *schema = merged;
+
+        // Continue processing the merged schema
+        simplify_schema(schema, location, ctx);
+        return;
+    }
+
+    // Handle oneOf/anyOf - keep them as a oneOf that typify can turn into an enum.
+    // anyOf is treated as oneOf: untagged deserialization picks the first match.
+    for keyword in ["oneOf", "anyOf"] {
+        if schema.get(keyword).is_some_and(|o| o.is_array()) {
+            unions::normalize_union(schema, keyword, location, ctx);
+            return;
+        }
+    }
+
+    // Recursively process nested schemas
+    for (path, child) in visit::subschemas_mut(schema) {
+        simplify_schema(child, &format!("{}/{}", location, path), ctx);
+    }
+}
+
+/// The JSON Schema type shared by the values of an `enum`, or `string` if
+/// they are mixed. Nulls (of nullable enums) are ignored.
+fn enum_value_type(values: Option<&Value>) -> &'static str {
+    let values: Vec<&Value> = values
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|v| !v.is_null())
+        .collect();
+
+    if values.is_empty() {
+        "string"
+    } else if values.iter().all(|v| v.is_i64() || v.is_u64()) {
+        "integer"
+    } else if values.iter().all(|v| v.is_number()) {
+        "number"
+    } else if values.iter().all(|v| v.is_boolean()) {
+        "boolean"
+    } else {
+        "string"
+    }
+}
+
+/// Merge an `allOf` member into `target`.
+///
+/// `$ref` members are resolved against `components.schemas` and nested
+/// `allOf`s are flattened. `visiting` holds the references currently being
+/// merged so that reference cycles are skipped instead of recursing forever.
+fn merge_into(
+    target: &mut Value,
+    source: &Value,
+    location: &str,
+    ctx: &mut Simplifier,
+    visiting: &mut Vec<String>,
+) {
+    if let Some(reference) = source.get("$ref").and_then(|r| r.as_str()) {
+        let resolved = reference
+            .strip_prefix("#/components/schemas/")
+            .filter(|name| !visiting.iter().any(|v| v == name))
+            .and_then(|name| ctx.schemas.get(name).cloned().map(|s| (name, s)));
+
+        match resolved {
+            Some((name, resolved)) => {
+                visiting.push(name.to_string());
+                merge_into(target, &resolved, location, ctx, visiting);
+                visiting.pop();
+            }
+            None => ctx.diagnostics.push(
+                Kind::UnresolvedRef,
+                location,
+                format!("allOf member {} is missing or cyclic", reference),
+            ),
+        }
+        return;
+    }
+
+    if let Some(all_of) = source.get("allOf").and_then(|a| a.as_array()) {
+        for item in all_of {
+            merge_into(target, item, location, ctx, visiting);
+        }
+    }
+
+    // Merge properties
+    if let Some(source_props) = source.get("properties").and_then(|p| p.as_object())
+        && let Some(target_props) = target
+            .as_object_mut()
+            .and_then(|o| o.entry("properties").or_insert(json!({})).as_object_mut())
+    {
+        for (key, value) in source_props {
+            target_props.insert(key.clone(), value.clone());
+        }
+    }
+
+    // Merge required fields
+    if let Some(source_required) = source.get("required").and_then(|r| r.as_array()) {
+        let target_required = target
+            .as_object_mut()
+            .and_then(|o| o.entry("required").or_insert(json!([])).as_array_mut());
+
+        if let Some(target_req) = target_required {
+            for item in source_required {
+                if !target_req.contains(item) {
+                    target_req.push(item.clone());
+                }
+            }
+        }
+    }
+
+    // Copy other fields if not present. A union is kept next to the merged
+    // properties, which `unions::normalize_union` merges into its members;
+    // only one union per keyword can be kept.
+    if let Some(source_obj) = source.as_object()
+        && let Some(target_obj) = target.as_object_mut()
+    {
+        for (key, value) in source_obj {
+            if key == "properties" || key == "required" || key == "allOf" {
+                continue;
+            }
+            if !target_obj.contains_key(key) {
+                target_obj.insert(key.clone(), value.clone());
+            } else if (key == "oneOf" || key == "anyOf") && target_obj[key] != *value {
+                ctx.diagnostics.push(
+                    Kind::CollapsedUnion,
+                    format!("{}/{}", location, key),
+                    format!("allOf has more than one {}, only the first is kept", key),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! Sending of the generated requests through the client's backend.
+//!
+//! Progenitor sends every request with `client.client.execute(request)`,
+//! directly on the `reqwest::Client`. Those calls are rewritten to
+//! `crate::hooks::execute(&client.inner, &client.client, request)`, which
+//! uses the `HttpBackend` configured in `ClientState`, if any, so that the
+//! operations do not depend on the transport.
+//!
+//! The responses are read with `ResponseValue::from_response(response)`,
+//! rewritten to `crate::hooks::from_response(&client.inner, response)` so
+//! that the client's `SchemaDrift` policy applies to them.
+
+use proc_macro2::TokenStream;
+use syn::visit_mut::{self, VisitMut};
+
+/// Route every request the generated code sends through the backend hook.
+pub fn route_through_backend(tokens: TokenStream) -> Result<TokenStream, syn::Error> {
+    let mut file: syn::File = syn::parse2(tokens)?;
+
+    let mut router = Router {
+        client: None,
+        rewritten: 0,
+    };
+    router.visit_file_mut(&mut file);
+    if router.rewritten == 0 {
+        return Err(syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "found no `client.execute(request)` calls to route through the backend",
+        ));
+    }
+
+    Ok(quote::quote!(#file))
+}
+
+struct Router {
+    /// The client sending the request of the current function.
+    client: Option<syn::Expr>,
+    rewritten: usize,
+}
+
+impl VisitMut for Router {
+    fn visit_impl_item_fn_mut(&mut self, function: &mut syn::ImplItemFn) {
+        self.client = None;
+        visit_mut::visit_impl_item_fn_mut(self, function);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        visit_mut::visit_expr_mut(self, expr);
+
+        if let syn::Expr::Call(call) = expr
+            && is_from_response(&call.func)
+            && let Some(client) = &self.client
+        {
+            let args = &call.args;
+            *expr = syn::parse_quote! {
+                crate::hooks::from_response(&#client.inner, #args)
+            };
+            return;
+        }
+
+        let syn::Expr::MethodCall(call) = expr else {
+            return;
+        };
+        if call.method != "execute" || call.args.len() != 1 {
+            return;
+        }
+        let syn::Expr::Field(field) = &*call.receiver else {
+            return;
+        };
+        if !matches!(&field.member, syn::Member::Named(name) if name == "client") {
+            return;
+        }
+
+        let client = (*field.base).clone();
+        let request = &call.args[0];
+        *expr = syn::parse_quote! {
+            crate::hooks::execute(&#client.inner, &#client.client, #request)
+        };
+        self.client = Some(client);
+        self.rewritten += 1;
+    }
+}
+
+/// Whether `func` is `ResponseValue::from_response`.
+fn is_from_response(func: &syn::Expr) -> bool {
+    let syn::Expr::Path(path) = func else {
+        return false;
+    };
+    let segments: Vec<_> = path.path.segments.iter().collect();
+    matches!(
+        segments.as_slice(),
+        [.., ty, function] if ty.ident == "ResponseValue" && function.ident == "from_response"
+    )
+}
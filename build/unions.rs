@@ -0,0 +1,208 @@
+//! Normalise `oneOf`/`anyOf` unions into shapes typify turns into Rust enums.
+//!
+//! Members are simplified and deduplicated, and the union is kept as a
+//! `oneOf`; how the resulting enum is tagged is left to typify. Keywords next
+//! to the union that shape the value, such as the `properties` every member
+//! shares, are merged into each member. Unions of plain string enums are
+//! merged into a single enum, and unions whose members only list `required`
+//! properties are dropped in favour of the schema next to them. Unions that
+//! cannot be represented are replaced by an unconstrained schema (generated
+//! as `serde_json::Value`) and recorded in the diagnostics report.
+
+use serde_json::{Map, Value, json};
+
+use crate::diagnostics::Kind;
+use crate::simplify::{Simplifier, simplify_schema};
+
+/// Keywords that describe the schema itself rather than its shape, and can
+/// therefore stay next to a `oneOf`.
+const ANNOTATIONS: &[&str] = &[
+    "title",
+    "description",
+    "nullable",
+    "readOnly",
+    "writeOnly",
+    "deprecated",
+    "example",
+    "default",
+    "externalDocs",
+    "discriminator",
+];
+
+/// Normalise the union stored under `keyword` (`oneOf` or `anyOf`) in `schema`.
+pub fn normalize_union(schema: &mut Value, keyword: &str, location: &str, ctx: &mut Simplifier) {
+    let Some(obj) = schema.as_object_mut() else {
+        return;
+    };
+    let Some(Value::Array(mut members)) = obj.remove(keyword) else {
+        return;
+    };
+    let union_location = format!("{}/{}", location, keyword);
+
+    // Keywords next to the union that shape the value, e.g. the properties
+    // shared by every member of `allOf: [{$ref: Base}, {oneOf: [...]}]`
+    let shape: Map<String, Value> = obj
+        .iter()
+        .filter(|(key, _)| !ANNOTATIONS.contains(&key.as_str()) && *key != "type")
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+
+    // `oneOf: [{required: [a]}, {required: [b]}]` only constrains the schema
+    // next to it, which is kept as it is
+    if !shape.is_empty() && members.iter().all(only_requires) {
+        ctx.diagnostics.push(
+            Kind::CollapsedUnion,
+            union_location,
+            "members only list required properties, kept the schema next to them",
+        );
+        simplify_schema(schema, location, ctx);
+        return;
+    }
+
+    // A sibling `type` applies to every member, e.g. `{type: string, oneOf: [{format: ipv4}, ...]}`
+    let sibling_type = obj.get("type").cloned();
+    if let Some(ty) = &sibling_type {
+        for member in members.iter_mut() {
+            if let Some(member) = member.as_object_mut()
+                && !member.contains_key("type")
+                && !member.contains_key("$ref")
+            {
+                member.insert("type".to_string(), ty.clone());
+            }
+        }
+        obj.remove("type");
+    }
+
+    // Every member gets the shape, and the merge below resolves `$ref` members
+    if !shape.is_empty() {
+        obj.retain(|key, _| !shape.contains_key(key));
+        for member in members.iter_mut() {
+            *member = json!({ "allOf": [Value::Object(shape.clone()), member.take()] });
+        }
+    }
+
+    for (i, member) in members.iter_mut().enumerate() {
+        simplify_schema(member, &format!("{}/{}", union_location, i), ctx);
+    }
+
+    // Identical members only produce duplicate variants
+    let mut unique: Vec<Value> = Vec::with_capacity(members.len());
+    for member in members {
+        if !unique.contains(&member) {
+            unique.push(member);
+        }
+    }
+    let mut members = unique;
+
+    let collapse_reason = if members.iter().any(accepts_anything) {
+        Some("a member accepts any value".to_string())
+    } else if members.iter().any(|m| m.get("not").is_some()) {
+        Some("a member uses `not`".to_string())
+    } else {
+        None
+    };
+
+    if let Some(reason) = collapse_reason {
+        ctx.diagnostics
+            .push(Kind::CollapsedUnion, union_location, reason);
+        let mut result = annotations_of(obj);
+        if let Some(ty) = sibling_type {
+            result.insert("type".to_string(), ty);
+        }
+        result.extend(shape);
+        *schema = Value::Object(result);
+        simplify_schema(schema, location, ctx);
+        return;
+    }
+
+    match members.len() {
+        0 => {
+            let mut result = annotations_of(obj);
+            result.extend(shape);
+            *schema = Value::Object(result);
+            simplify_schema(schema, location, ctx);
+        }
+        1 => {
+            let mut member = members.remove(0);
+            if let Some(member_obj) = member.as_object_mut() {
+                if member_obj.contains_key("$ref") {
+                    // Siblings of `$ref` are ignored, so keep the reference alone
+                    *schema = member;
+                    return;
+                }
+                for (key, value) in annotations_of(obj) {
+                    member_obj.entry(key).or_insert(value);
+                }
+            }
+            *schema = member;
+        }
+        _ => {
+            if let Some(Value::Object(merged)) = merge_string_enums(&members) {
+                let mut result = annotations_of(obj);
+                result.extend(merged);
+                *schema = Value::Object(result);
+                return;
+            }
+
+            let mut result = annotations_of(obj);
+            result.insert("oneOf".to_string(), Value::Array(members));
+            *schema = Value::Object(result);
+        }
+    }
+}
+
+fn annotations_of(obj: &Map<String, Value>) -> Map<String, Value> {
+    obj.iter()
+        .filter(|(key, _)| ANNOTATIONS.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+/// Whether a union member only lists properties that must be present.
+fn only_requires(member: &Value) -> bool {
+    member.as_object().is_some_and(|obj| {
+        obj.contains_key("required")
+            && obj
+                .keys()
+                .all(|key| key == "required" || ANNOTATIONS.contains(&key.as_str()))
+    })
+}
+
+/// Whether a schema places no constraints on its value.
+fn accepts_anything(schema: &Value) -> bool {
+    schema.as_object().is_some_and(|obj| {
+        obj.keys()
+            .all(|key| ANNOTATIONS.contains(&key.as_str()) && key != "discriminator")
+    })
+}
+
+/// Merge a union of plain string enums (`oneOf: [{enum: [a]}, {enum: [b]}]`)
+/// into a single enum.
+fn merge_string_enums(members: &[Value]) -> Option<Value> {
+    let mut values: Vec<Value> = Vec::new();
+
+    for member in members {
+        let obj = member.as_object()?;
+        if obj.get("type").is_some_and(|t| t != "string") {
+            return None;
+        }
+        if obj.keys().any(|key| {
+            !matches!(
+                key.as_str(),
+                "type" | "enum" | "description" | "title" | "example"
+            )
+        }) {
+            return None;
+        }
+        for value in obj.get("enum")?.as_array()? {
+            if !values.contains(value) {
+                values.push(value.clone());
+            }
+        }
+    }
+
+    Some(serde_json::json!({
+        "type": "string",
+        "enum": values,
+    }))
+}
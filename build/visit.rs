@@ -0,0 +1,151 @@
+//! Every schema position of an OpenAPI document.
+//!
+//! [`for_each_schema`] finds the outermost schema of each position: component
+//! schemas, parameters, headers, and request and response bodies, both under
+//! `components` and inline in operations. [`subschemas_mut`] finds the
+//! schemas nested in a schema. Together they reach every schema in the
+//! document, named or not.
+
+use serde_json::Value;
+
+use crate::filter::is_operation_method;
+
+/// Call `visit` with every outermost schema in `spec` and its location.
+///
+/// Locations are the component name for `components.schemas`, and otherwise
+/// a path such as `GET /zones responses/200/application/json`.
+pub fn for_each_schema(spec: &mut Value, visit: &mut dyn FnMut(&mut Value, &str)) {
+    if let Some(components) = spec.get_mut("components").and_then(|c| c.as_object_mut()) {
+        for (kind, entries) in components.iter_mut() {
+            let Some(entries) = entries.as_object_mut() else {
+                continue;
+            };
+            for (name, entry) in entries.iter_mut() {
+                let location = format!("{}/{}", kind, name);
+                match kind.as_str() {
+                    "schemas" => visit(entry, name),
+                    "parameters" | "headers" => parameter(entry, &location, visit),
+                    "requestBodies" => content(entry, &location, visit),
+                    "responses" => response(entry, &location, visit),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let Some(paths) = spec.get_mut("paths").and_then(|p| p.as_object_mut()) else {
+        return;
+    };
+    for (path, item) in paths.iter_mut() {
+        let Some(item) = item.as_object_mut() else {
+            continue;
+        };
+        for (key, value) in item.iter_mut() {
+            if key == "parameters" {
+                parameters(value, path, visit);
+                continue;
+            }
+            if !is_operation_method(key) {
+                continue;
+            }
+
+            let operation_name = format!("{} {}", key.to_uppercase(), path);
+            let Some(operation) = value.as_object_mut() else {
+                continue;
+            };
+            for (key, value) in operation.iter_mut() {
+                match key.as_str() {
+                    "parameters" => parameters(value, &operation_name, visit),
+                    "requestBody" => {
+                        content(value, &format!("{} requestBody", operation_name), visit)
+                    }
+                    "responses" => {
+                        let Some(responses) = value.as_object_mut() else {
+                            continue;
+                        };
+                        for (status, value) in responses.iter_mut() {
+                            let location = format!("{} responses/{}", operation_name, status);
+                            response(value, &location, visit);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// The schemas nested directly in `schema`, with their location relative to
+/// it (e.g. `properties/name`, `items`, `oneOf/2`).
+pub fn subschemas_mut(schema: &mut Value) -> Vec<(String, &mut Value)> {
+    let mut children = Vec::new();
+    let Some(obj) = schema.as_object_mut() else {
+        return children;
+    };
+
+    for (key, value) in obj.iter_mut() {
+        match (key.as_str(), value) {
+            ("properties" | "patternProperties", Value::Object(schemas)) => {
+                for (name, child) in schemas.iter_mut() {
+                    children.push((format!("{}/{}", key, name), child));
+                }
+            }
+            // `items` is an array of schemas in tuple form
+            ("items" | "prefixItems" | "allOf" | "oneOf" | "anyOf", Value::Array(schemas)) => {
+                for (i, child) in schemas.iter_mut().enumerate() {
+                    children.push((format!("{}/{}", key, i), child));
+                }
+            }
+            // `additionalProperties` may also be a boolean
+            ("items" | "additionalProperties" | "additionalItems" | "not" | "contains", child)
+                if child.is_object() =>
+            {
+                children.push((key.clone(), child));
+            }
+            _ => {}
+        }
+    }
+
+    children
+}
+
+fn parameters(list: &mut Value, location: &str, visit: &mut dyn FnMut(&mut Value, &str)) {
+    for value in list.as_array_mut().into_iter().flatten() {
+        let name = value
+            .get("name")
+            .and_then(|n| n.as_str())
+            .unwrap_or("$ref")
+            .to_string();
+        parameter(value, &format!("{} parameters/{}", location, name), visit);
+    }
+}
+
+/// A parameter or header: a `schema`, or one schema per media type.
+fn parameter(value: &mut Value, location: &str, visit: &mut dyn FnMut(&mut Value, &str)) {
+    if let Some(schema) = value.get_mut("schema") {
+        visit(schema, location);
+    }
+    content(value, location, visit);
+}
+
+fn response(value: &mut Value, location: &str, visit: &mut dyn FnMut(&mut Value, &str)) {
+    content(value, location, visit);
+
+    if let Some(headers) = value.get_mut("headers").and_then(|h| h.as_object_mut()) {
+        for (name, header) in headers.iter_mut() {
+            parameter(header, &format!("{}/headers/{}", location, name), visit);
+        }
+    }
+}
+
+/// The schema of each media type of a request or response body.
+fn content(value: &mut Value, location: &str, visit: &mut dyn FnMut(&mut Value, &str)) {
+    let Some(content) = value.get_mut("content").and_then(|c| c.as_object_mut()) else {
+        return;
+    };
+    for (media_type, media) in content.iter_mut() {
+        if let Some(schema) = media.get_mut("schema") {
+            visit(schema, &format!("{}/{}", location, media_type));
+        }
+    }
+}
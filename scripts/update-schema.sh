@@ -0,0 +1,25 @@
+#!/usr/bin/env sh
+# Refresh the vendored Cloudflare OpenAPI schema snapshot.
+#
+# Usage: scripts/update-schema.sh [URL]
+set -eu
+
+cd "$(dirname "$0")/.."
+
+url="${1:-https://developers.cloudflare.com/api/openapi.json}"
+tmp="$(mktemp)"
+trap 'rm -f "$tmp"' EXIT
+
+echo "Downloading $url"
+curl --fail --silent --show-error --location "$url" --output "$tmp"
+
+mkdir -p schema
+gzip -9 -n -c "$tmp" > schema/openapi.json.gz
+printf '%s  openapi.json\n' "$(sha256sum "$tmp" | cut -d ' ' -f 1)" > schema/openapi.json.sha256
+
+echo "Updated schema/openapi.json.gz ($(cut -d ' ' -f 1 schema/openapi.json.sha256))"
+
+# Record names for new operations; existing names are kept, and the build
+# fails if an operation that has a name is no longer in the schema
+CLOUDFLARE_API_UPDATE_OPERATION_NAMES=1 cargo build --quiet
+echo "Updated schema/operation_names.json"
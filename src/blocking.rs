@@ -0,0 +1,148 @@
+//! A synchronous client (`blocking` feature).
+//!
+//! [`Client`] has the same operation methods as the async
+//! [`Client`](crate::Client); the `send()` of their builders blocks instead
+//! of returning a future:
+//!
+//! ```ignore
+//! use cloudflare_api::ResponseExt;
+//!
+//! let client = cloudflare_api::blocking::Client::builder()
+//!     .from_env()?
+//!     .build_blocking()?;
+//!
+//! let zones: Vec<serde_json::Value> = client.zones_list().send().into_result()?;
+//! ```
+//!
+//! Like `reqwest::blocking`, the client drives requests on a small runtime
+//! of its own, so callers need no async runtime, and it must not be used
+//! from within one.
+
+use std::future::Future;
+use std::sync::Arc;
+
+use futures::executor::block_on_stream;
+use serde::de::DeserializeOwned;
+use tokio::runtime::Runtime;
+
+use crate::{
+    ClientBuilder, CloudflareError, DEFAULT_PER_PAGE, PageRequest, PaginationStyle, ResponseExt,
+};
+
+// Generated from the async client: the `builder` module and one method per
+// operation
+include!(concat!(env!("OUT_DIR"), "/blocking.rs"));
+
+/// A synchronous Cloudflare API client.
+///
+/// Clones share the runtime and the underlying async client.
+#[derive(Clone, Debug)]
+pub struct Client {
+    inner: crate::Client,
+    runtime: Arc<Runtime>,
+}
+
+impl Client {
+    /// Start building an authenticated client; finish with
+    /// [`ClientBuilder::build_blocking`].
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    /// Drive an async client synchronously.
+    pub fn from_async(client: crate::Client) -> std::io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(Client {
+            inner: client,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// The async client that sends the requests.
+    pub fn as_async(&self) -> &crate::Client {
+        &self.inner
+    }
+
+    /// Wait for a future on this client's runtime, e.g. a call the blocking
+    /// client has no counterpart for.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+}
+
+/// Iterates every item of a paginated list operation, fetching pages as they
+/// are needed. The blocking counterpart of [`crate::Paginator`]:
+///
+/// ```ignore
+/// use cloudflare_api::PaginationStyle;
+/// use cloudflare_api::blocking::Paginator;
+///
+/// let records: Vec<serde_json::Value> = Paginator::new(PaginationStyle::Page, |page| {
+///     client
+///         .dns_records_list()
+///         .zone_id(zone_id)
+///         .page(page.page.unwrap_or(1) as f64)
+///         .per_page(page.per_page as f64)
+///         .send()
+/// })
+/// .per_page(100)
+/// .collect_all()?;
+/// ```
+pub struct Paginator<F> {
+    fetch: F,
+    style: PaginationStyle,
+    per_page: u64,
+}
+
+impl<F, R> Paginator<F>
+where
+    F: FnMut(PageRequest) -> R,
+    R: ResponseExt,
+{
+    /// Paginate the operation called by `fetch`.
+    pub fn new(style: PaginationStyle, fetch: F) -> Self {
+        Paginator {
+            fetch,
+            style,
+            per_page: DEFAULT_PER_PAGE,
+        }
+    }
+
+    /// Paginate a generated method, looking its style up by name.
+    ///
+    /// Returns `None` if the method is not paginated.
+    pub fn for_method(method: &str, fetch: F) -> Option<Self> {
+        PaginationStyle::of(method).map(|style| Self::new(style, fetch))
+    }
+
+    /// Number of items requested per page.
+    pub fn per_page(mut self, per_page: u64) -> Self {
+        self.per_page = per_page.max(1);
+        self
+    }
+
+    /// Iterate every item of every page.
+    pub fn into_items<T>(self) -> impl Iterator<Item = Result<T, CloudflareError>>
+    where
+        T: DeserializeOwned,
+    {
+        let mut fetch = self.fetch;
+        let stream = crate::Paginator::new(self.style, move |page| std::future::ready(fetch(page)))
+            .per_page(self.per_page)
+            .into_stream();
+
+        // Pages are fetched by the blocking `fetch`, so the stream never waits
+        // on I/O itself
+        block_on_stream(Box::pin(stream))
+    }
+
+    /// Fetch every page and collect all items.
+    pub fn collect_all<T>(self) -> Result<Vec<T>, CloudflareError>
+    where
+        T: DeserializeOwned,
+    {
+        self.into_items().collect()
+    }
+}
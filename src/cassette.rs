@@ -0,0 +1,875 @@
+//! Record real Cloudflare exchanges once and replay them offline
+//! (`cassette` feature).
+//!
+//! A [`Cassette`] is a local HTTP server the client is pointed at, so it
+//! works with any `reqwest::Client` passed to `Client::new_with_client`. In
+//! record mode it forwards every request to the real API and stores the
+//! exchange; in replay mode it answers from the stored exchanges and fails
+//! loudly on a request that was not recorded.
+//!
+//! ```ignore
+//! use cloudflare_api::cassette::Cassette;
+//!
+//! // Replays tests/cassettes/zones.yaml, or records it when
+//! // CLOUDFLARE_API_CASSETTE=record is set
+//! let cassette = Cassette::start("tests/cassettes/zones.yaml").await?;
+//! let client = cassette.client();
+//!
+//! let zones: Vec<serde_json::Value> = client.zones_list().send().await.into_result()?;
+//!
+//! cassette.finish()?;
+//! ```
+//!
+//! Credentials, tokens and account IDs are scrubbed before anything is
+//! written, see [`Scrubber`]. Requests are matched by method, path, query and
+//! body, see [`Matcher`]. Bodies that are not UTF-8 are stored as base64.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::task::JoinHandle;
+
+use crate::mock::{MockRequest, MockResponse, lock, serve};
+use crate::{Client, ClientBuilder, Credentials, DEFAULT_BASE_URL, RetryPolicy};
+
+/// Set to `record` to record cassettes started with [`Cassette::start`].
+pub const CASSETTE_MODE_ENV: &str = "CLOUDFLARE_API_CASSETTE";
+
+/// Replaces scrubbed secrets.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Replaces account IDs found in paths.
+pub const ACCOUNT_ID_PLACEHOLDER: &str = "ACCOUNT_ID";
+
+/// Headers whose values are always scrubbed.
+const SECRET_HEADERS: &[&str] = &[
+    "authorization",
+    "cookie",
+    "set-cookie",
+    "x-auth-email",
+    "x-auth-key",
+    "x-auth-user-service-key",
+];
+
+/// Response fields whose string values are always scrubbed, as `(path,
+/// field)`: `field` of any object in the response to a request whose path
+/// contains `path`.
+const SECRET_FIELDS: &[(&str, &str)] = &[
+    // Created API tokens, and the new value of a rolled one
+    ("/tokens", "value"),
+    ("/tokens", "result"),
+    ("/access/service_tokens", "client_secret"),
+];
+
+/// Headers that are not recorded: hop-by-hop, or set again on replay.
+const SKIPPED_HEADERS: &[&str] = &[
+    "accept-encoding",
+    "connection",
+    "content-length",
+    "date",
+    "host",
+    "keep-alive",
+    "transfer-encoding",
+];
+
+/// Whether a cassette talks to the real API or answers from its file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Forward requests to the API and store the exchanges.
+    Record,
+    /// Answer from the stored exchanges.
+    Replay,
+}
+
+/// How a stored body is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyEncoding {
+    /// Base64, for bodies that are not UTF-8.
+    Base64,
+}
+
+/// A stored request, after scrubbing.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub body: String,
+    /// Set when `body` is not the body's text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<BodyEncoding>,
+}
+
+/// A stored response, after scrubbing.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub body: String,
+    /// Set when `body` is not the body's text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<BodyEncoding>,
+}
+
+/// One request and the response it got.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// The contents of a cassette file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Recording {
+    interactions: Vec<Interaction>,
+}
+
+type Match = Arc<dyn Fn(&RecordedRequest, &RecordedRequest) -> bool + Send + Sync>;
+
+/// Decides which recorded request a replayed request corresponds to.
+///
+/// By default method, path, query (in any parameter order) and body (as JSON
+/// when both sides parse) must all be equal.
+#[derive(Clone)]
+pub struct Matcher {
+    query: bool,
+    body: bool,
+    custom: Option<Match>,
+}
+
+impl Default for Matcher {
+    fn default() -> Self {
+        Matcher {
+            query: true,
+            body: true,
+            custom: None,
+        }
+    }
+}
+
+impl Matcher {
+    /// Match on method, path, query and body.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Do not compare query strings, e.g. for time-based filters.
+    pub fn ignore_query(mut self) -> Self {
+        self.query = false;
+        self
+    }
+
+    /// Do not compare bodies, e.g. for generated names.
+    pub fn ignore_body(mut self) -> Self {
+        self.body = false;
+        self
+    }
+
+    /// Match with `matches(recorded, actual)` instead of the built-in rules.
+    pub fn custom<F>(matches: F) -> Self
+    where
+        F: Fn(&RecordedRequest, &RecordedRequest) -> bool + Send + Sync + 'static,
+    {
+        Matcher {
+            custom: Some(Arc::new(matches)),
+            ..Self::default()
+        }
+    }
+
+    /// Whether `actual` is a replay of `recorded`.
+    pub fn matches(&self, recorded: &RecordedRequest, actual: &RecordedRequest) -> bool {
+        if let Some(custom) = &self.custom {
+            return custom(recorded, actual);
+        }
+
+        recorded.method.eq_ignore_ascii_case(&actual.method)
+            && recorded.path == actual.path
+            && (!self.query || query_pairs(&recorded.query) == query_pairs(&actual.query))
+            && (!self.body
+                || (recorded.encoding == actual.encoding
+                    && same_body(&recorded.body, &actual.body)))
+    }
+}
+
+impl fmt::Debug for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Matcher")
+            .field("query", &self.query)
+            .field("body", &self.body)
+            .field("custom", &self.custom.is_some())
+            .finish()
+    }
+}
+
+fn query_pairs(query: &Option<String>) -> Vec<&str> {
+    let mut pairs: Vec<&str> = query
+        .as_deref()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .collect();
+    pairs.sort_unstable();
+    pairs
+}
+
+fn same_body(recorded: &str, actual: &str) -> bool {
+    match (
+        serde_json::from_str::<serde_json::Value>(recorded),
+        serde_json::from_str::<serde_json::Value>(actual),
+    ) {
+        (Ok(recorded), Ok(actual)) => recorded == actual,
+        _ => recorded == actual,
+    }
+}
+
+/// Removes secrets from exchanges before they are written.
+///
+/// By default the values of credential and cookie headers are replaced with
+/// [`REDACTED`] wherever they appear, and so are API token values and
+/// service token secrets in responses. Account IDs found in
+/// `/accounts/{account_id}` paths or in the `account.id` of a response are
+/// replaced with [`ACCOUNT_ID_PLACEHOLDER`]. A secret seen in one exchange
+/// is removed from the whole recording. Replayed requests are scrubbed the
+/// same way before matching, so tests may use any account ID.
+///
+/// Bodies stored as base64 are not scrubbed.
+#[derive(Clone, Debug)]
+pub struct Scrubber {
+    headers: Vec<String>,
+    fields: Vec<(String, String)>,
+    secrets: Vec<(String, String)>,
+    account_ids: bool,
+}
+
+impl Default for Scrubber {
+    fn default() -> Self {
+        Scrubber {
+            headers: SECRET_HEADERS.iter().map(|h| h.to_string()).collect(),
+            fields: SECRET_FIELDS
+                .iter()
+                .map(|(path, field)| (path.to_string(), field.to_string()))
+                .collect(),
+            secrets: Vec::new(),
+            account_ids: true,
+        }
+    }
+}
+
+impl Scrubber {
+    /// Scrub credentials, cookies and account IDs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also scrub the value of `header`.
+    pub fn header(mut self, header: &str) -> Self {
+        self.headers.push(header.to_ascii_lowercase());
+        self
+    }
+
+    /// Also scrub the string `field` of any object in responses to requests
+    /// whose path contains `path`; an empty `path` matches every request.
+    pub fn field(mut self, path: &str, field: &str) -> Self {
+        self.fields.push((path.to_string(), field.to_string()));
+        self
+    }
+
+    /// Replace `secret` with `placeholder` everywhere, e.g. a zone ID or an
+    /// email address.
+    pub fn secret(mut self, secret: impl Into<String>, placeholder: impl Into<String>) -> Self {
+        let secret = secret.into();
+        if !secret.is_empty() {
+            self.secrets.push((secret, placeholder.into()));
+        }
+        self
+    }
+
+    /// Keep account IDs in paths and bodies.
+    pub fn keep_account_ids(mut self) -> Self {
+        self.account_ids = false;
+        self
+    }
+
+    /// Scrub an exchange. Secrets found in it (header values, account IDs
+    /// and secret response fields) are added to `learned`, the secrets of the
+    /// whole recording, and removed from the exchange.
+    fn scrub(
+        &self,
+        learned: &mut BTreeSet<(String, String)>,
+        request: &mut RecordedRequest,
+        mut response: Option<&mut RecordedResponse>,
+    ) {
+        for (name, value) in &mut request.headers {
+            if self.headers.contains(name) {
+                let value = std::mem::replace(value, REDACTED.to_string());
+                let token = value.strip_prefix("Bearer ").unwrap_or(&value);
+                learn(learned, token, REDACTED);
+            }
+        }
+        if self.account_ids {
+            let segments: Vec<&str> = request.path.split('/').collect();
+            for pair in segments.windows(2) {
+                if pair[0] == "accounts" {
+                    learn(learned, pair[1], ACCOUNT_ID_PLACEHOLDER);
+                }
+            }
+        }
+
+        if let Some(response) = response.as_deref_mut() {
+            for (name, value) in &mut response.headers {
+                if self.headers.contains(name) {
+                    *value = REDACTED.to_string();
+                }
+            }
+            if response.encoding.is_none()
+                && let Ok(body) = serde_json::from_str::<Value>(&response.body)
+            {
+                let fields: Vec<&str> = self
+                    .fields
+                    .iter()
+                    .filter(|(path, _)| request.path.contains(path.as_str()))
+                    .map(|(_, field)| field.as_str())
+                    .collect();
+                self.learn_fields(learned, &body, &fields);
+            }
+        }
+
+        self.redact(learned, request, response);
+    }
+
+    /// Learn the secret `fields` and account IDs of a response body.
+    fn learn_fields(
+        &self,
+        learned: &mut BTreeSet<(String, String)>,
+        value: &Value,
+        fields: &[&str],
+    ) {
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object {
+                    if let Value::String(secret) = value
+                        && fields.contains(&key.as_str())
+                    {
+                        learn(learned, secret, REDACTED);
+                    }
+                    if self.account_ids
+                        && key == "account"
+                        && let Some(id) = value.get("id").and_then(Value::as_str)
+                    {
+                        learn(learned, id, ACCOUNT_ID_PLACEHOLDER);
+                    }
+                    self.learn_fields(learned, value, fields);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.learn_fields(learned, item, fields);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Replace the configured and `learned` secrets in an exchange, longest
+    /// first so a secret containing another is replaced whole.
+    fn redact(
+        &self,
+        learned: &BTreeSet<(String, String)>,
+        request: &mut RecordedRequest,
+        response: Option<&mut RecordedResponse>,
+    ) {
+        let mut secrets: Vec<&(String, String)> = self.secrets.iter().chain(learned).collect();
+        secrets.sort_by_key(|(secret, _)| Reverse(secret.len()));
+
+        let scrub = |text: &mut String| {
+            for (secret, placeholder) in &secrets {
+                if text.contains(secret.as_str()) {
+                    *text = text.replace(secret.as_str(), placeholder);
+                }
+            }
+        };
+        scrub(&mut request.path);
+        if let Some(query) = &mut request.query {
+            scrub(query);
+        }
+        if request.encoding.is_none() {
+            scrub(&mut request.body);
+        }
+        request.headers.values_mut().for_each(scrub);
+
+        if let Some(response) = response {
+            response.headers.values_mut().for_each(scrub);
+            if response.encoding.is_none() {
+                scrub(&mut response.body);
+            }
+        }
+    }
+}
+
+/// Add `secret` to `learned`, unless it is empty or already a placeholder.
+fn learn(learned: &mut BTreeSet<(String, String)>, secret: &str, placeholder: &str) {
+    if !secret.is_empty() && secret != REDACTED && secret != ACCOUNT_ID_PLACEHOLDER {
+        learned.insert((secret.to_string(), placeholder.to_string()));
+    }
+}
+
+/// Configures a [`Cassette`] before it starts.
+#[derive(Debug)]
+pub struct CassetteBuilder {
+    path: PathBuf,
+    upstream: String,
+    matcher: Matcher,
+    scrubber: Scrubber,
+}
+
+impl CassetteBuilder {
+    /// Record from `upstream` instead of [`DEFAULT_BASE_URL`].
+    pub fn upstream(mut self, upstream: impl Into<String>) -> Self {
+        self.upstream = upstream.into();
+        self
+    }
+
+    /// Match replayed requests with `matcher`.
+    pub fn matcher(mut self, matcher: Matcher) -> Self {
+        self.matcher = matcher;
+        self
+    }
+
+    /// Scrub exchanges with `scrubber`.
+    pub fn scrubber(mut self, scrubber: Scrubber) -> Self {
+        self.scrubber = scrubber;
+        self
+    }
+
+    /// Record when [`CASSETTE_MODE_ENV`] is `record`, replay otherwise.
+    pub async fn start(self) -> io::Result<Cassette> {
+        let mode = match std::env::var(CASSETTE_MODE_ENV).as_deref() {
+            Ok("record") => Mode::Record,
+            _ => Mode::Replay,
+        };
+        self.start_in(mode).await
+    }
+
+    /// Record, overwriting the cassette when it is finished.
+    pub async fn record(self) -> io::Result<Cassette> {
+        self.start_in(Mode::Record).await
+    }
+
+    /// Replay; fails if the cassette does not exist.
+    pub async fn replay(self) -> io::Result<Cassette> {
+        self.start_in(Mode::Replay).await
+    }
+
+    async fn start_in(self, mode: Mode) -> io::Result<Cassette> {
+        let interactions = match mode {
+            Mode::Record => Vec::new(),
+            Mode::Replay => {
+                read(&self.path)
+                    .map_err(|e| {
+                        io::Error::new(
+                            e.kind(),
+                            format!(
+                                "cannot replay cassette {}: {}; record it with {}=record",
+                                self.path.display(),
+                                e,
+                                CASSETTE_MODE_ENV
+                            ),
+                        )
+                    })?
+                    .interactions
+            }
+        };
+
+        let state = Arc::new(State {
+            mode,
+            path: self.path,
+            upstream: self.upstream.trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+            matcher: self.matcher,
+            scrubber: self.scrubber,
+            secrets: Mutex::new(BTreeSet::new()),
+            used: Mutex::new(vec![false; interactions.len()]),
+            interactions: Mutex::new(interactions),
+            unmatched: Mutex::new(Vec::new()),
+        });
+
+        let (addr, task) = serve({
+            let state = state.clone();
+            move |request| {
+                let state = state.clone();
+                async move {
+                    match state.mode {
+                        Mode::Record => state.record(request).await,
+                        Mode::Replay => state.replay(request),
+                    }
+                }
+            }
+        })
+        .await?;
+
+        Ok(Cassette {
+            addr,
+            state,
+            task,
+            finished: false,
+        })
+    }
+}
+
+/// A local server that records or replays Cloudflare API exchanges.
+///
+/// Call [`Cassette::finish`] at the end of the test. A recording cassette is
+/// also written when dropped, and a replaying cassette that saw unmatched
+/// requests panics when dropped.
+pub struct Cassette {
+    addr: SocketAddr,
+    state: Arc<State>,
+    task: JoinHandle<()>,
+    finished: bool,
+}
+
+struct State {
+    mode: Mode,
+    path: PathBuf,
+    upstream: String,
+    http: reqwest::Client,
+    matcher: Matcher,
+    scrubber: Scrubber,
+    /// Secrets learned so far, as `(secret, placeholder)`.
+    secrets: Mutex<BTreeSet<(String, String)>>,
+    interactions: Mutex<Vec<Interaction>>,
+    /// Which interactions were replayed already.
+    used: Mutex<Vec<bool>>,
+    /// Replayed requests that matched nothing, as `METHOD /path?query`.
+    unmatched: Mutex<Vec<String>>,
+}
+
+impl Cassette {
+    /// Configure a cassette stored at `path` (`.yaml`/`.yml` for YAML,
+    /// anything else for JSON).
+    pub fn builder(path: impl Into<PathBuf>) -> CassetteBuilder {
+        CassetteBuilder {
+            path: path.into(),
+            upstream: DEFAULT_BASE_URL.to_string(),
+            matcher: Matcher::default(),
+            scrubber: Scrubber::default(),
+        }
+    }
+
+    /// Record when [`CASSETTE_MODE_ENV`] is `record`, replay otherwise.
+    pub async fn start(path: impl Into<PathBuf>) -> io::Result<Self> {
+        Self::builder(path).start().await
+    }
+
+    /// Record the cassette at `path` from the real API.
+    pub async fn record(path: impl Into<PathBuf>) -> io::Result<Self> {
+        Self::builder(path).record().await
+    }
+
+    /// Replay the cassette at `path`.
+    pub async fn replay(path: impl Into<PathBuf>) -> io::Result<Self> {
+        Self::builder(path).replay().await
+    }
+
+    /// Whether the cassette records or replays.
+    pub fn mode(&self) -> Mode {
+        self.state.mode
+    }
+
+    /// Base URL to point a client at.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// A builder for a client of this cassette, without retries.
+    ///
+    /// Credentials come from the environment (see [`Credentials::from_env`]);
+    /// they are only needed to record.
+    pub fn client_builder(&self) -> ClientBuilder {
+        let builder = Client::builder()
+            .base_url(self.url())
+            .retry_policy(RetryPolicy::none());
+        match Credentials::from_env() {
+            Ok(credentials) => builder.credentials(credentials),
+            Err(_) => builder.api_token(REDACTED),
+        }
+    }
+
+    /// A client of this cassette, see [`Cassette::client_builder`].
+    pub fn client(&self) -> Client {
+        self.client_builder()
+            .build()
+            .expect("a cassette client needs no configuration that can fail")
+    }
+
+    /// The exchanges recorded, or loaded for replay.
+    pub fn interactions(&self) -> Vec<Interaction> {
+        lock(&self.state.interactions).clone()
+    }
+
+    /// Write a recording cassette, or report the requests a replaying
+    /// cassette could not match.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.complete()
+    }
+
+    fn complete(&mut self) -> io::Result<()> {
+        self.finished = true;
+        match self.state.mode {
+            Mode::Record => {
+                // Secrets learned late may appear in earlier exchanges
+                let secrets = lock(&self.state.secrets);
+                let mut interactions = lock(&self.state.interactions);
+                for interaction in interactions.iter_mut() {
+                    self.state.scrubber.redact(
+                        &secrets,
+                        &mut interaction.request,
+                        Some(&mut interaction.response),
+                    );
+                }
+                write(
+                    &self.state.path,
+                    &Recording {
+                        interactions: interactions.clone(),
+                    },
+                )
+            }
+            Mode::Replay => {
+                let unmatched = lock(&self.state.unmatched);
+                if unmatched.is_empty() {
+                    Ok(())
+                } else {
+                    Err(io::Error::other(format!(
+                        "cassette {} has no interaction for: {}",
+                        self.state.path.display(),
+                        unmatched.join(", ")
+                    )))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Cassette {
+    fn drop(&mut self) {
+        self.task.abort();
+        if !self.finished
+            && let Err(error) = self.complete()
+            && !std::thread::panicking()
+        {
+            panic!("{}", error);
+        }
+    }
+}
+
+impl fmt::Debug for Cassette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cassette")
+            .field("url", &self.url())
+            .field("mode", &self.state.mode)
+            .field("path", &self.state.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl State {
+    /// Forward a request to the API and store the scrubbed exchange.
+    async fn record(&self, request: MockRequest) -> MockResponse {
+        let url = match &request.query {
+            Some(query) => format!("{}{}?{}", self.upstream, request.path, query),
+            None => format!("{}{}", self.upstream, request.path),
+        };
+        let method =
+            reqwest::Method::from_bytes(request.method.as_bytes()).unwrap_or(reqwest::Method::GET);
+        let mut headers = request.headers.clone();
+        for header in SKIPPED_HEADERS {
+            headers.remove(*header);
+        }
+
+        let response = self
+            .http
+            .request(method, url)
+            .headers(headers)
+            .body(request.body.clone())
+            .send()
+            .await;
+        let (status, response_headers, body) = match response {
+            Ok(response) => {
+                let status = response.status().as_u16();
+                let headers = response.headers().clone();
+                match response.bytes().await {
+                    Ok(body) => (status, headers, body.to_vec()),
+                    Err(error) => return upstream_error(&error),
+                }
+            }
+            Err(error) => return upstream_error(&error),
+        };
+
+        let mut recorded_request = recorded_request(&request);
+        let (recorded_body, encoding) = encode_body(&body);
+        let mut recorded_response = RecordedResponse {
+            status,
+            headers: header_map(&response_headers),
+            body: recorded_body,
+            encoding,
+        };
+        self.scrubber.scrub(
+            &mut lock(&self.secrets),
+            &mut recorded_request,
+            Some(&mut recorded_response),
+        );
+        lock(&self.interactions).push(Interaction {
+            request: recorded_request,
+            response: recorded_response.clone(),
+        });
+
+        let mut response = MockResponse::raw(status, body);
+        for (name, value) in &response_headers {
+            if let Ok(value) = value.to_str()
+                && !SKIPPED_HEADERS.contains(&name.as_str())
+            {
+                response = response.header(name.as_str(), value);
+            }
+        }
+        response
+    }
+
+    /// Answer from the first unused interaction that matches.
+    fn replay(&self, request: MockRequest) -> MockResponse {
+        let mut actual = recorded_request(&request);
+        self.scrubber
+            .scrub(&mut lock(&self.secrets), &mut actual, None);
+
+        let interactions = lock(&self.interactions);
+        let mut used = lock(&self.used);
+        let found = interactions.iter().enumerate().find(|(i, interaction)| {
+            !used[*i] && self.matcher.matches(&interaction.request, &actual)
+        });
+
+        match found {
+            Some((i, interaction)) => {
+                used[i] = true;
+                let recorded = &interaction.response;
+                let Some(body) = decode_body(&recorded.body, recorded.encoding) else {
+                    return MockResponse::error(
+                        500,
+                        0,
+                        &format!(
+                            "cassette {} has a response body that is not valid base64",
+                            self.path.display()
+                        ),
+                    );
+                };
+                let mut response = MockResponse::raw(recorded.status, body);
+                for (name, value) in &recorded.headers {
+                    response = response.header(name, value);
+                }
+                response
+            }
+            None => {
+                let description = match &actual.query {
+                    Some(query) => format!("{} {}?{}", actual.method, actual.path, query),
+                    None => format!("{} {}", actual.method, actual.path),
+                };
+                eprintln!(
+                    "cassette {}: no recorded interaction matches {}",
+                    self.path.display(),
+                    description
+                );
+                let message = format!(
+                    "cassette {} has no interaction for {}",
+                    self.path.display(),
+                    description
+                );
+                lock(&self.unmatched).push(description);
+                MockResponse::error(500, 0, &message)
+            }
+        }
+    }
+}
+
+fn recorded_request(request: &MockRequest) -> RecordedRequest {
+    let (body, encoding) = encode_body(&request.body);
+    RecordedRequest {
+        method: request.method.clone(),
+        path: request.path.clone(),
+        query: request.query.clone(),
+        headers: header_map(&request.headers),
+        body,
+        encoding,
+    }
+}
+
+/// A body as stored: its text, or base64 if it is not UTF-8.
+fn encode_body(body: &[u8]) -> (String, Option<BodyEncoding>) {
+    match std::str::from_utf8(body) {
+        Ok(text) => (text.to_string(), None),
+        Err(_) => (BASE64.encode(body), Some(BodyEncoding::Base64)),
+    }
+}
+
+fn decode_body(body: &str, encoding: Option<BodyEncoding>) -> Option<Vec<u8>> {
+    match encoding {
+        None => Some(body.as_bytes().to_vec()),
+        Some(BodyEncoding::Base64) => BASE64.decode(body).ok(),
+    }
+}
+
+fn header_map(headers: &reqwest::header::HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .filter(|(name, _)| !SKIPPED_HEADERS.contains(&name.as_str()))
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+fn upstream_error(error: &reqwest::Error) -> MockResponse {
+    MockResponse::error(
+        502,
+        0,
+        &format!("cassette could not reach the API: {}", error),
+    )
+}
+
+fn is_yaml(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == "yaml" || ext == "yml")
+}
+
+fn read(path: &Path) -> io::Result<Recording> {
+    let content = fs::read_to_string(path)?;
+    if is_yaml(path) {
+        serde_yaml::from_str(&content).map_err(io::Error::other)
+    } else {
+        serde_json::from_str(&content).map_err(io::Error::other)
+    }
+}
+
+fn write(path: &Path, recording: &Recording) -> io::Result<()> {
+    let content = if is_yaml(path) {
+        serde_yaml::to_string(recording).map_err(io::Error::other)?
+    } else {
+        serde_json::to_string_pretty(recording)? + "\n"
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)
+}
@@ -0,0 +1,360 @@
+//! Authenticated construction of the generated [`Client`].
+
+use std::env;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, InvalidHeaderValue, USER_AGENT};
+
+use crate::{Client, ClientState, HttpBackend, RateLimiter, RetryPolicy, SchemaDrift};
+
+/// Base URL of the Cloudflare v4 API.
+pub const DEFAULT_BASE_URL: &str = "https://api.cloudflare.com/client/v4";
+
+/// A secret string that is redacted from `Debug` output.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    /// Wrap a secret value.
+    pub fn new(secret: impl Into<String>) -> Self {
+        Secret(secret.into())
+    }
+
+    /// Access the secret value.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+impl From<String> for Secret {
+    fn from(secret: String) -> Self {
+        Secret(secret)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(secret: &str) -> Self {
+        Secret(secret.to_string())
+    }
+}
+
+/// How requests are authenticated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Credentials {
+    /// An API token, sent as `Authorization: Bearer <token>`.
+    ApiToken(Secret),
+    /// The legacy Global API Key, sent as `X-Auth-Email` and `X-Auth-Key`.
+    GlobalApiKey {
+        /// Email address of the account owner.
+        email: String,
+        /// The Global API Key.
+        key: Secret,
+    },
+    /// An Origin CA key, sent as `X-Auth-User-Service-Key`.
+    UserServiceKey(Secret),
+}
+
+impl Credentials {
+    /// Read credentials from `CLOUDFLARE_API_TOKEN`, or from
+    /// `CLOUDFLARE_EMAIL` and `CLOUDFLARE_API_KEY`.
+    pub fn from_env() -> Result<Self, BuildError> {
+        if let Ok(token) = env::var("CLOUDFLARE_API_TOKEN") {
+            return Ok(Credentials::ApiToken(token.into()));
+        }
+
+        match (env::var("CLOUDFLARE_EMAIL"), env::var("CLOUDFLARE_API_KEY")) {
+            (Ok(email), Ok(key)) => Ok(Credentials::GlobalApiKey {
+                email,
+                key: key.into(),
+            }),
+            _ => Err(BuildError::MissingCredentials),
+        }
+    }
+
+    /// Read credentials from the `CLOUDFLARE_API_TOKEN` secret of a Worker,
+    /// or from its `CLOUDFLARE_EMAIL` variable and `CLOUDFLARE_API_KEY`
+    /// secret.
+    ///
+    /// Workers have no process environment for [`Credentials::from_env`].
+    #[cfg(all(target_arch = "wasm32", feature = "worker"))]
+    pub fn from_worker_env(env: &worker::Env) -> Result<Self, BuildError> {
+        if let Ok(token) = env.secret("CLOUDFLARE_API_TOKEN") {
+            return Ok(Credentials::ApiToken(token.to_string().into()));
+        }
+
+        match (
+            env.var("CLOUDFLARE_EMAIL"),
+            env.secret("CLOUDFLARE_API_KEY"),
+        ) {
+            (Ok(email), Ok(key)) => Ok(Credentials::GlobalApiKey {
+                email: email.to_string(),
+                key: key.to_string().into(),
+            }),
+            _ => Err(BuildError::MissingCredentials),
+        }
+    }
+
+    fn headers(&self) -> Result<HeaderMap, BuildError> {
+        let mut headers = HeaderMap::new();
+
+        match self {
+            Credentials::ApiToken(token) => {
+                let value = format!("Bearer {}", token.expose());
+                headers.insert(reqwest::header::AUTHORIZATION, sensitive(&value)?);
+            }
+            Credentials::GlobalApiKey { email, key } => {
+                headers.insert(
+                    HeaderName::from_static("x-auth-email"),
+                    HeaderValue::from_str(email)?,
+                );
+                headers.insert(
+                    HeaderName::from_static("x-auth-key"),
+                    sensitive(key.expose())?,
+                );
+            }
+            Credentials::UserServiceKey(key) => {
+                headers.insert(
+                    HeaderName::from_static("x-auth-user-service-key"),
+                    sensitive(key.expose())?,
+                );
+            }
+        }
+
+        Ok(headers)
+    }
+}
+
+fn sensitive(value: &str) -> Result<HeaderValue, InvalidHeaderValue> {
+    let mut value = HeaderValue::from_str(value)?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+/// Builds an authenticated [`Client`].
+///
+/// ```no_run
+/// # fn main() -> Result<(), cloudflare_api::BuildError> {
+/// let client = cloudflare_api::Client::builder()
+///     .api_token("my-token")
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct ClientBuilder {
+    base_url: Option<String>,
+    credentials: Option<Credentials>,
+    timeout: Option<Duration>,
+    user_agent: Option<String>,
+    retry: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+    backend: Option<Arc<dyn HttpBackend>>,
+    schema_drift: SchemaDrift,
+}
+
+impl ClientBuilder {
+    /// Create a builder without credentials, pointing at [`DEFAULT_BASE_URL`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use a different API base URL, e.g. a mock server.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Authenticate with an API token.
+    pub fn api_token(self, token: impl Into<Secret>) -> Self {
+        self.credentials(Credentials::ApiToken(token.into()))
+    }
+
+    /// Authenticate with the account email and Global API Key.
+    pub fn global_api_key(self, email: impl Into<String>, key: impl Into<Secret>) -> Self {
+        self.credentials(Credentials::GlobalApiKey {
+            email: email.into(),
+            key: key.into(),
+        })
+    }
+
+    /// Authenticate with an Origin CA key.
+    pub fn user_service_key(self, key: impl Into<Secret>) -> Self {
+        self.credentials(Credentials::UserServiceKey(key.into()))
+    }
+
+    /// Authenticate with the given credentials.
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Authenticate with credentials read from the environment, see
+    /// [`Credentials::from_env`].
+    pub fn from_env(self) -> Result<Self, BuildError> {
+        Ok(self.credentials(Credentials::from_env()?))
+    }
+
+    /// Authenticate with credentials read from a Worker's bindings, see
+    /// [`Credentials::from_worker_env`].
+    #[cfg(all(target_arch = "wasm32", feature = "worker"))]
+    pub fn from_worker_env(self, env: &worker::Env) -> Result<Self, BuildError> {
+        Ok(self.credentials(Credentials::from_worker_env(env)?))
+    }
+
+    /// Set a timeout for each request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Override the `User-Agent` header.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Retry policy every request is sent again with.
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Wait on `rate_limiter` before every request.
+    ///
+    /// Clones of the built client share the limiter; pass a clone of the same
+    /// limiter to several builders to share it between clients.
+    pub fn rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Send requests with `backend` instead of a `reqwest::Client` of the
+    /// client's own.
+    pub fn backend(mut self, backend: impl HttpBackend + 'static) -> Self {
+        self.backend = Some(Arc::new(backend));
+        self
+    }
+
+    /// How to handle responses that do not match the schema: fail (the
+    /// default), or drop the values that do not match and log them.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), cloudflare_api::BuildError> {
+    /// let client = cloudflare_api::Client::builder()
+    ///     .api_token("my-token")
+    ///     .schema_drift(cloudflare_api::SchemaDrift::Log)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn schema_drift(mut self, policy: SchemaDrift) -> Self {
+        self.schema_drift = policy;
+        self
+    }
+
+    /// Build the client.
+    pub fn build(self) -> Result<Client, BuildError> {
+        let mut headers = match &self.credentials {
+            Some(credentials) => credentials.headers()?,
+            None => HeaderMap::new(),
+        };
+
+        let user_agent = self.user_agent.unwrap_or_else(|| {
+            concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")).to_string()
+        });
+
+        let http = reqwest::Client::builder()
+            .default_headers(headers.clone())
+            .user_agent(&user_agent)
+            .build()?;
+
+        let base_url = self.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL);
+
+        // The timeout is set per request, as the `wasm32` client has no
+        // client-wide timeout and a custom backend never sees the client
+        let mut state = ClientState::new(self.retry).with_schema_drift(self.schema_drift);
+        if let Some(timeout) = self.timeout {
+            state = state.with_timeout(timeout);
+        }
+        if let Some(rate_limiter) = self.rate_limiter {
+            state = state.with_rate_limiter(rate_limiter);
+        }
+        if let Some(backend) = self.backend {
+            headers.insert(USER_AGENT, HeaderValue::from_str(&user_agent)?);
+            state = state.with_backend(backend, headers);
+        }
+
+        Ok(Client::new_with_client(base_url, http, state))
+    }
+
+    /// Build a synchronous [`blocking::Client`](crate::blocking::Client).
+    #[cfg(feature = "blocking")]
+    pub fn build_blocking(self) -> Result<crate::blocking::Client, BuildError> {
+        crate::blocking::Client::from_async(self.build()?).map_err(BuildError::Runtime)
+    }
+}
+
+impl Client {
+    /// Start building an authenticated client.
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+}
+
+/// An error while building a [`Client`].
+#[derive(Debug)]
+pub enum BuildError {
+    /// No credentials were found in the environment.
+    MissingCredentials,
+    /// A credential contains characters that are not valid in a header.
+    InvalidHeader(InvalidHeaderValue),
+    /// The HTTP client could not be created.
+    Http(reqwest::Error),
+    /// The runtime of a blocking client could not be started.
+    Runtime(std::io::Error),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::MissingCredentials => write!(
+                f,
+                "Set CLOUDFLARE_API_TOKEN, or CLOUDFLARE_EMAIL and CLOUDFLARE_API_KEY"
+            ),
+            BuildError::InvalidHeader(e) => write!(f, "Invalid credential: {}", e),
+            BuildError::Http(e) => write!(f, "Failed to build HTTP client: {}", e),
+            BuildError::Runtime(e) => write!(f, "Failed to start blocking runtime: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BuildError::MissingCredentials => None,
+            BuildError::InvalidHeader(e) => Some(e),
+            BuildError::Http(e) => Some(e),
+            BuildError::Runtime(e) => Some(e),
+        }
+    }
+}
+
+impl From<InvalidHeaderValue> for BuildError {
+    fn from(e: InvalidHeaderValue) -> Self {
+        BuildError::InvalidHeader(e)
+    }
+}
+
+impl From<reqwest::Error> for BuildError {
+    fn from(e: reqwest::Error) -> Self {
+        BuildError::Http(e)
+    }
+}
@@ -0,0 +1,150 @@
+//! Responses that do not match the schema.
+//!
+//! The live API does not always agree with its spec: it returns values of
+//! another type, or `null` where the spec promises a value. By default such a
+//! response fails with [`Error::InvalidResponsePayload`], as with any
+//! Progenitor client. With [`SchemaDrift::Log`] the values that do not fit are
+//! dropped instead, each one logged as a warning, and the rest of the
+//! response is returned.
+//!
+//! Fields the spec does not declare never fail: they are ignored, or with the
+//! `lenient` feature kept in each type's `extra` map.
+
+use progenitor_client::{Error, ResponseValue};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// How a response that does not match the schema is handled, see
+/// [`ClientBuilder::schema_drift`](crate::ClientBuilder::schema_drift).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SchemaDrift {
+    /// Fail with [`Error::InvalidResponsePayload`].
+    #[default]
+    Error,
+    /// Drop the values that do not match, log them, and return the rest.
+    Log,
+}
+
+/// Give up on a response after dropping this many values.
+const MAX_DROPPED: usize = 32;
+
+/// Deserialize the JSON body of `response`, in place of
+/// `ResponseValue::from_response`.
+pub(crate) async fn from_response<T: DeserializeOwned, E>(
+    policy: SchemaDrift,
+    response: reqwest::Response,
+) -> Result<ResponseValue<T>, Error<E>> {
+    let status = response.status();
+    let headers = response.headers().clone();
+    let url = response.url().clone();
+    let body = response.bytes().await.map_err(Error::ResponseBodyError)?;
+
+    let error = match serde_json::from_slice(&body) {
+        Ok(inner) => return Ok(ResponseValue::new(inner, status, headers)),
+        Err(error) => error,
+    };
+    if policy == SchemaDrift::Error {
+        return Err(Error::InvalidResponsePayload(body, error));
+    }
+
+    match repair(&body) {
+        Some((inner, dropped)) => {
+            for (pointer, reason) in dropped {
+                log::warn!(
+                    "{}: dropped `{}` from the response, it does not match the schema: {}",
+                    url,
+                    pointer,
+                    reason
+                );
+            }
+            Ok(ResponseValue::new(inner, status, headers))
+        }
+        None => Err(Error::InvalidResponsePayload(body, error)),
+    }
+}
+
+/// Deserialize `body` into `T`, dropping the values that fail until it
+/// succeeds. Returns the JSON pointer of each dropped value with the reason.
+///
+/// serde_json only reports the line of an error, so the body is
+/// pretty-printed, which puts every value on a line of its own. An error at
+/// the closing line of an object or array (a missing field, too few items)
+/// drops the whole object or array.
+fn repair<T: DeserializeOwned>(body: &[u8]) -> Option<(T, Vec<(String, String)>)> {
+    let mut value: Value = serde_json::from_slice(body).ok()?;
+    let mut dropped = Vec::new();
+
+    while dropped.len() < MAX_DROPPED {
+        let pretty = serde_json::to_string_pretty(&value).ok()?;
+        let error = match serde_json::from_str(&pretty) {
+            Ok(inner) => return Some((inner, dropped)),
+            Err(error) => error,
+        };
+
+        let mut line = 1;
+        let pointer = pointer_at_line(&value, String::new(), &mut line, error.line())?;
+        // Without the whole document there is nothing left to return
+        if pointer.is_empty() {
+            return None;
+        }
+        remove(&mut value, &pointer)?;
+        dropped.push((pointer, error.to_string()));
+    }
+
+    None
+}
+
+/// The JSON pointer of the value on `target` (1-based) of the
+/// pretty-printed `value`, which starts on `*line`. The opening and closing
+/// lines of an object or array belong to it.
+fn pointer_at_line(
+    value: &Value,
+    pointer: String,
+    line: &mut usize,
+    target: usize,
+) -> Option<String> {
+    if *line == target {
+        return Some(pointer);
+    }
+
+    let children: Vec<(String, &Value)> = match value {
+        Value::Object(obj) => obj.iter().map(|(k, v)| (escape(k), v)).collect(),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i.to_string(), v))
+            .collect(),
+        _ => return None,
+    };
+    // `{}` and `[]` take one line
+    if children.is_empty() {
+        return None;
+    }
+
+    for (key, child) in children {
+        *line += 1;
+        if let Some(found) = pointer_at_line(child, format!("{}/{}", pointer, key), line, target) {
+            return Some(found);
+        }
+    }
+
+    *line += 1;
+    (*line == target).then_some(pointer)
+}
+
+/// Remove the value at `pointer` from its object or array.
+fn remove(value: &mut Value, pointer: &str) -> Option<Value> {
+    let (parent, key) = pointer.rsplit_once('/')?;
+    match value.pointer_mut(parent)? {
+        Value::Object(obj) => obj.remove(&key.replace("~1", "/").replace("~0", "~")),
+        Value::Array(items) => {
+            let index: usize = key.parse().ok()?;
+            (index < items.len()).then(|| items.remove(index))
+        }
+        _ => None,
+    }
+}
+
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
@@ -0,0 +1,168 @@
+//! The `{success, errors, messages, result, result_info}` envelope that wraps
+//! every Cloudflare v4 response.
+
+use progenitor_client::{Error, ResponseValue};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::CloudflareError;
+
+/// A Cloudflare v4 response envelope.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Envelope<T> {
+    /// Whether the API call was successful.
+    #[serde(default)]
+    pub success: bool,
+    /// Errors reported by the API. Non-empty when `success` is `false`.
+    #[serde(default)]
+    pub errors: Vec<ResponseMessage>,
+    /// Informational messages.
+    #[serde(default)]
+    pub messages: Vec<ResponseMessage>,
+    /// The payload of the response.
+    #[serde(default = "none")]
+    pub result: Option<T>,
+    /// Pagination information for list endpoints.
+    #[serde(default)]
+    pub result_info: Option<ResultInfo>,
+}
+
+// `#[serde(default)]` would require `T: Default`
+fn none<T>() -> Option<T> {
+    None
+}
+
+/// A message in the `errors` or `messages` array of an envelope.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ResponseMessage {
+    /// Cloudflare error or message code.
+    pub code: i64,
+    /// Human readable description.
+    pub message: String,
+    /// Link to documentation about this error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub documentation_url: Option<String>,
+    /// The part of the request that caused the error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<MessageSource>,
+    /// Underlying errors that led to this one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub error_chain: Vec<ResponseMessage>,
+}
+
+/// Location in the request that a [`ResponseMessage`] refers to.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MessageSource {
+    /// JSON pointer into the request body, e.g. `/content`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pointer: Option<String>,
+}
+
+/// Pagination information returned by list endpoints.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ResultInfo {
+    /// Current page, starting at 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u64>,
+    /// Number of results per page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_page: Option<u64>,
+    /// Number of results on this page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u64>,
+    /// Total number of results.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_count: Option<u64>,
+    /// Total number of pages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_pages: Option<u64>,
+    /// Cursor for the next page (cursor-paginated endpoints).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// Cursors for the surrounding pages (cursor-paginated endpoints).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursors: Option<Cursors>,
+}
+
+/// Cursors for cursor-paginated endpoints.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Cursors {
+    /// Cursor for the previous page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    /// Cursor for the next page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+}
+
+/// The unwrapped payload of a successful response, with the envelope
+/// metadata that is still useful to callers.
+#[derive(Clone, Debug)]
+pub struct ApiResponse<T> {
+    /// The payload of the response.
+    pub result: T,
+    /// Informational messages.
+    pub messages: Vec<ResponseMessage>,
+    /// Pagination information for list endpoints.
+    pub result_info: Option<ResultInfo>,
+}
+
+impl<T> Envelope<T> {
+    /// Unwrap the envelope, turning `success: false` into an error.
+    pub fn into_api_response(self) -> Result<ApiResponse<T>, CloudflareError> {
+        if !self.success {
+            return Err(CloudflareError::from_errors(None, self.errors));
+        }
+
+        let result = self.result.ok_or(CloudflareError::MissingResult)?;
+
+        Ok(ApiResponse {
+            result,
+            messages: self.messages,
+            result_info: self.result_info,
+        })
+    }
+
+    /// Unwrap the envelope and return only its `result`.
+    pub fn into_result(self) -> Result<T, CloudflareError> {
+        self.into_api_response().map(|response| response.result)
+    }
+}
+
+/// Unwrap the envelope of a generated operation's response.
+///
+/// The generated types are converted into `T` through their JSON
+/// representation, so `T` may be the generated `result` type or any type
+/// that deserializes from it:
+///
+/// ```ignore
+/// use cloudflare_api::ResponseExt;
+/// use cloudflare_api::prelude::*;
+///
+/// let zones: Vec<cloudflare_api::zones::types::Zone> = client.zones_list().send().await.into_result()?;
+/// ```
+pub trait ResponseExt {
+    /// Unwrap the envelope, keeping `messages` and `result_info`.
+    fn into_api_response<T: DeserializeOwned>(self) -> Result<ApiResponse<T>, CloudflareError>;
+
+    /// Unwrap the envelope and return only its `result`.
+    fn into_result<T: DeserializeOwned>(self) -> Result<T, CloudflareError>
+    where
+        Self: Sized,
+    {
+        self.into_api_response().map(|response| response.result)
+    }
+}
+
+impl<B, E> ResponseExt for Result<ResponseValue<B>, Error<E>>
+where
+    B: Serialize,
+    E: Serialize,
+{
+    fn into_api_response<T: DeserializeOwned>(self) -> Result<ApiResponse<T>, CloudflareError> {
+        let value = self.map_err(CloudflareError::from)?.into_inner();
+        let envelope: Envelope<T> = serde_json::to_value(value).and_then(serde_json::from_value)?;
+
+        envelope.into_api_response()
+    }
+}
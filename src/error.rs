@@ -0,0 +1,237 @@
+//! Errors returned when unwrapping Cloudflare responses.
+//!
+//! Well-known Cloudflare error codes are mapped to dedicated variants so that
+//! retry and alerting logic can match on them; everything else ends up in
+//! [`CloudflareError::Other`].
+
+use std::fmt;
+
+use progenitor_client::Error;
+use reqwest::StatusCode;
+use serde::Serialize;
+
+use crate::{Envelope, ResponseMessage};
+
+/// Authentication failures, e.g. a missing or invalid API token.
+const AUTHENTICATION_CODES: &[i64] = &[10000, 9103, 9106, 9109];
+
+/// Rate limiting.
+const RATE_LIMIT_CODES: &[i64] = &[10013, 971];
+
+/// A DNS record with the same name or content already exists.
+const RECORD_EXISTS_CODES: &[i64] = &[81053, 81057, 81058];
+
+/// The zone identifier does not exist or is not accessible.
+const ZONE_NOT_FOUND_CODES: &[i64] = &[1001];
+
+/// The request was rejected as malformed or invalid.
+const VALIDATION_CODES: &[i64] = &[1004, 6003, 6007, 9207];
+
+/// An error returned by the Cloudflare API or while talking to it.
+#[derive(Debug)]
+pub enum CloudflareError {
+    /// Authentication failed (e.g. code 10000).
+    Authentication(ApiError),
+    /// The request was rate limited (codes 10013 and 971, or HTTP 429).
+    RateLimited(ApiError),
+    /// The DNS record already exists, or conflicts with a CNAME (codes 81053,
+    /// 81057 and 81058).
+    RecordAlreadyExists(ApiError),
+    /// The zone does not exist (code 1001).
+    ZoneNotFound(ApiError),
+    /// The request was invalid; see [`ResponseMessage::source`] for the field.
+    Validation(ApiError),
+    /// Any other error reported by the API.
+    Other {
+        /// Code of the first reported error, `0` if none was reported.
+        code: i64,
+        /// The full error response.
+        error: ApiError,
+    },
+    /// The API reported success but the response has no `result`.
+    MissingResult,
+    /// The response could not be decoded as a Cloudflare envelope.
+    Decode(serde_json::Error),
+    /// The request failed before a Cloudflare response was received.
+    Client(Box<Error>),
+}
+
+/// The `errors` array of a failed Cloudflare response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiError {
+    /// HTTP status of the response, if known.
+    pub status: Option<StatusCode>,
+    /// The reported errors, in the order returned by the API.
+    pub errors: Vec<ResponseMessage>,
+}
+
+impl ApiError {
+    /// Code of the first reported error.
+    pub fn code(&self) -> Option<i64> {
+        self.errors.first().map(|e| e.code)
+    }
+
+    /// All codes reported, including those in error chains.
+    pub fn codes(&self) -> Vec<i64> {
+        fn walk(messages: &[ResponseMessage], codes: &mut Vec<i64>) {
+            for message in messages {
+                codes.push(message.code);
+                walk(&message.error_chain, codes);
+            }
+        }
+
+        let mut codes = Vec::new();
+        walk(&self.errors, &mut codes);
+        codes
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<String> = self
+            .errors
+            .iter()
+            .map(
+                |e| match e.source.as_ref().and_then(|s| s.pointer.as_deref()) {
+                    Some(pointer) => format!("{} ({}) at {}", e.message, e.code, pointer),
+                    None => format!("{} ({})", e.message, e.code),
+                },
+            )
+            .collect();
+
+        match (messages.is_empty(), self.status) {
+            (true, Some(status)) => write!(f, "HTTP {}", status),
+            (true, None) => write!(f, "unknown error"),
+            (false, _) => write!(f, "{}", messages.join(", ")),
+        }
+    }
+}
+
+impl CloudflareError {
+    /// Map an `errors` array to the variant of its first well-known code.
+    pub(crate) fn from_errors(status: Option<StatusCode>, errors: Vec<ResponseMessage>) -> Self {
+        let error = ApiError { status, errors };
+
+        for code in error.codes() {
+            if AUTHENTICATION_CODES.contains(&code) {
+                return CloudflareError::Authentication(error);
+            }
+            if RATE_LIMIT_CODES.contains(&code) {
+                return CloudflareError::RateLimited(error);
+            }
+            if RECORD_EXISTS_CODES.contains(&code) {
+                return CloudflareError::RecordAlreadyExists(error);
+            }
+            if ZONE_NOT_FOUND_CODES.contains(&code) {
+                return CloudflareError::ZoneNotFound(error);
+            }
+            if VALIDATION_CODES.contains(&code) {
+                return CloudflareError::Validation(error);
+            }
+        }
+
+        let has_pointer = error
+            .errors
+            .iter()
+            .any(|e| e.source.as_ref().is_some_and(|s| s.pointer.is_some()));
+
+        match status {
+            Some(StatusCode::TOO_MANY_REQUESTS) => CloudflareError::RateLimited(error),
+            Some(StatusCode::UNAUTHORIZED) => CloudflareError::Authentication(error),
+            _ if has_pointer => CloudflareError::Validation(error),
+            _ => CloudflareError::Other {
+                code: error.code().unwrap_or(0),
+                error,
+            },
+        }
+    }
+
+    /// The error response returned by the API, if there was one.
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            CloudflareError::Authentication(error)
+            | CloudflareError::RateLimited(error)
+            | CloudflareError::RecordAlreadyExists(error)
+            | CloudflareError::ZoneNotFound(error)
+            | CloudflareError::Validation(error)
+            | CloudflareError::Other { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Code of the first error reported by the API.
+    pub fn code(&self) -> Option<i64> {
+        self.api_error().and_then(ApiError::code)
+    }
+
+    /// HTTP status of the failed response, if there was one.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            CloudflareError::Client(e) => e.status(),
+            _ => self.api_error().and_then(|e| e.status),
+        }
+    }
+
+    /// Whether the request was rejected by rate limiting.
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(self, CloudflareError::RateLimited(_))
+    }
+}
+
+impl fmt::Display for CloudflareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CloudflareError::Authentication(e) => write!(f, "Authentication failed: {}", e),
+            CloudflareError::RateLimited(e) => write!(f, "Rate limited: {}", e),
+            CloudflareError::RecordAlreadyExists(e) => write!(f, "Record already exists: {}", e),
+            CloudflareError::ZoneNotFound(e) => write!(f, "Zone not found: {}", e),
+            CloudflareError::Validation(e) => write!(f, "Invalid request: {}", e),
+            CloudflareError::Other { error, .. } => write!(f, "Cloudflare API error: {}", error),
+            CloudflareError::MissingResult => write!(f, "Cloudflare response has no result"),
+            CloudflareError::Decode(e) => write!(f, "Invalid Cloudflare response: {}", e),
+            CloudflareError::Client(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CloudflareError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CloudflareError::Decode(e) => Some(e),
+            CloudflareError::Client(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for CloudflareError {
+    fn from(e: serde_json::Error) -> Self {
+        CloudflareError::Decode(e)
+    }
+}
+
+impl<E: Serialize> From<Error<E>> for CloudflareError {
+    fn from(e: Error<E>) -> Self {
+        // Error responses carry an envelope too; surface its `errors`
+        if let Error::ErrorResponse(response) = &e {
+            let envelope = serde_json::to_value(&**response)
+                .and_then(serde_json::from_value::<Envelope<serde_json::Value>>);
+
+            if let Ok(envelope) = envelope
+                && !envelope.errors.is_empty()
+            {
+                return CloudflareError::from_errors(Some(response.status()), envelope.errors);
+            }
+        }
+
+        match e.status() {
+            Some(status)
+                if status == StatusCode::TOO_MANY_REQUESTS
+                    || status == StatusCode::UNAUTHORIZED =>
+            {
+                CloudflareError::from_errors(Some(status), Vec::new())
+            }
+            _ => CloudflareError::Client(Box::new(e.into_untyped())),
+        }
+    }
+}
@@ -0,0 +1,1026 @@
+//! A stateful fake of the core zones, DNS and Workers KV endpoints (`mock`
+//! feature).
+//!
+//! [`FakeCloudflare`] runs on a [`MockServer`], so the generated client is
+//! used unmodified, but unlike the canned responses it remembers what was
+//! written: zones can be created, DNS records added, listed and deleted, and
+//! KV values written and read back. Requests are validated the way Cloudflare
+//! validates them and fail with Cloudflare's error codes, so error handling
+//! can be tested too. Every other operation gets the mock server's canned
+//! response.
+//!
+//! ```ignore
+//! use cloudflare_api::dns::types::DnsRecordsDnsRecordPost;
+//! use cloudflare_api::fake::FakeCloudflare;
+//! use serde_json::json;
+//!
+//! let fake = FakeCloudflare::start().await?;
+//! let zone_id = fake.add_zone("example.com");
+//! let client = fake.client();
+//!
+//! let record: DnsRecordsDnsRecordPost =
+//!     serde_json::from_value(json!({ "type": "A", "name": "www", "content": "192.0.2.1" }))?;
+//! client
+//!     .dns_records_create()
+//!     .zone_id(&zone_id)
+//!     .body(record)
+//!     .send()
+//!     .await
+//!     .into_result::<serde_json::Value>()?;
+//!
+//! let records: Vec<serde_json::Value> = client
+//!     .dns_records_list()
+//!     .zone_id(&zone_id)
+//!     .send()
+//!     .await
+//!     .into_result()?;
+//! assert_eq!(records[0]["name"], "www.example.com");
+//! ```
+//!
+//! Supported endpoints:
+//!
+//! - `/zones` and `/zones/{zone_id}`: list, create, get, edit, delete
+//! - `/zones/{zone_id}/dns_records[/{dns_record_id}]`: list, create, get,
+//!   update, edit, delete
+//! - `/accounts/{account_id}/storage/kv/namespaces[/{namespace_id}]`: list,
+//!   create, delete
+//! - `.../namespaces/{namespace_id}/keys`, `/values/{key_name}` and
+//!   `/metadata/{key_name}`: list keys, write, read and delete values
+
+use std::collections::BTreeMap;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{Map, Value, json};
+
+use crate::mock::{MockRequest, MockResponse, MockServer, lock};
+use crate::{Client, ClientBuilder};
+
+/// Account that owns the zones and namespaces the fake creates by default.
+pub const FAKE_ACCOUNT_ID: &str = "01a7362d577a6c3019a474fd6f485823";
+
+/// Authentication error.
+const AUTHENTICATION_ERROR: i64 = 10000;
+/// Malformed JSON in request body.
+const MALFORMED_JSON: i64 = 6007;
+/// Invalid zone identifier.
+const INVALID_ZONE: i64 = 1001;
+/// The zone name is not a registered domain.
+const INVALID_ZONE_NAME: i64 = 1049;
+/// The zone already exists.
+const ZONE_EXISTS: i64 = 1061;
+/// DNS validation error; the cause is in `error_chain`.
+const DNS_VALIDATION: i64 = 1004;
+/// DNS name is invalid.
+const INVALID_DNS_NAME: i64 = 9000;
+/// The record type cannot be proxied.
+const NOT_PROXIABLE: i64 = 9004;
+/// Content for an A record is not an IPv4 address.
+const INVALID_A_CONTENT: i64 = 9005;
+/// Content for an AAAA record is not an IPv6 address.
+const INVALID_AAAA_CONTENT: i64 = 9006;
+/// TTL is neither 1 (automatic) nor between 60 and 86400 seconds.
+const INVALID_TTL: i64 = 9021;
+/// The DNS record does not exist.
+const RECORD_NOT_FOUND: i64 = 81044;
+/// An A, AAAA or CNAME record with that name already exists.
+const CNAME_CONFLICT: i64 = 81053;
+/// An identical record already exists.
+const RECORD_EXISTS: i64 = 81057;
+/// The KV key does not exist.
+const KEY_NOT_FOUND: i64 = 10009;
+/// The KV namespace does not exist.
+const NAMESPACE_NOT_FOUND: i64 = 10011;
+/// A namespace with this title already exists.
+const NAMESPACE_EXISTS: i64 = 10014;
+
+/// Record types the fake accepts.
+const RECORD_TYPES: &[&str] = &[
+    "A", "AAAA", "CAA", "CERT", "CNAME", "DNSKEY", "DS", "HTTPS", "LOC", "MX", "NAPTR", "NS",
+    "PTR", "SMIMEA", "SRV", "SSHFP", "SVCB", "TLSA", "TXT", "URI",
+];
+
+/// Record types that may be proxied.
+const PROXIABLE_TYPES: &[&str] = &["A", "AAAA", "CNAME"];
+
+type Outcome = Result<MockResponse, MockResponse>;
+type Route = (
+    &'static str,
+    &'static str,
+    fn(&mut Store, &MockRequest) -> Outcome,
+);
+
+/// The endpoints the fake implements.
+const ROUTES: &[Route] = &[
+    ("GET", "/zones", zones_list),
+    ("POST", "/zones", zones_create),
+    ("GET", "/zones/{zone_id}", zones_get),
+    ("PATCH", "/zones/{zone_id}", zones_edit),
+    ("DELETE", "/zones/{zone_id}", zones_delete),
+    ("GET", "/zones/{zone_id}/dns_records", dns_records_list),
+    ("POST", "/zones/{zone_id}/dns_records", dns_records_create),
+    (
+        "GET",
+        "/zones/{zone_id}/dns_records/{dns_record_id}",
+        dns_records_get,
+    ),
+    (
+        "PUT",
+        "/zones/{zone_id}/dns_records/{dns_record_id}",
+        dns_records_update,
+    ),
+    (
+        "PATCH",
+        "/zones/{zone_id}/dns_records/{dns_record_id}",
+        dns_records_edit,
+    ),
+    (
+        "DELETE",
+        "/zones/{zone_id}/dns_records/{dns_record_id}",
+        dns_records_delete,
+    ),
+    (
+        "GET",
+        "/accounts/{account_id}/storage/kv/namespaces",
+        namespaces_list,
+    ),
+    (
+        "POST",
+        "/accounts/{account_id}/storage/kv/namespaces",
+        namespaces_create,
+    ),
+    (
+        "DELETE",
+        "/accounts/{account_id}/storage/kv/namespaces/{namespace_id}",
+        namespaces_delete,
+    ),
+    (
+        "GET",
+        "/accounts/{account_id}/storage/kv/namespaces/{namespace_id}/keys",
+        keys_list,
+    ),
+    (
+        "GET",
+        "/accounts/{account_id}/storage/kv/namespaces/{namespace_id}/values/{key_name}",
+        values_get,
+    ),
+    (
+        "PUT",
+        "/accounts/{account_id}/storage/kv/namespaces/{namespace_id}/values/{key_name}",
+        values_put,
+    ),
+    (
+        "DELETE",
+        "/accounts/{account_id}/storage/kv/namespaces/{namespace_id}/values/{key_name}",
+        values_delete,
+    ),
+    (
+        "GET",
+        "/accounts/{account_id}/storage/kv/namespaces/{namespace_id}/metadata/{key_name}",
+        metadata_get,
+    ),
+];
+
+/// A Cloudflare API that remembers zones, DNS records and KV values.
+#[derive(Debug)]
+pub struct FakeCloudflare {
+    mock: MockServer,
+    store: Arc<Mutex<Store>>,
+}
+
+#[derive(Debug, Default)]
+struct Store {
+    /// Zones in creation order.
+    zones: Vec<Value>,
+    /// DNS records by zone id, in creation order.
+    records: BTreeMap<String, Vec<Value>>,
+    /// KV namespaces in creation order.
+    namespaces: Vec<Namespace>,
+}
+
+#[derive(Debug)]
+struct Namespace {
+    id: String,
+    account_id: String,
+    title: String,
+    keys: BTreeMap<String, Entry>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    value: Vec<u8>,
+    metadata: Option<Value>,
+    /// Unix time after which the key is gone.
+    expiration: Option<u64>,
+}
+
+impl FakeCloudflare {
+    /// Start a fake on a free localhost port.
+    pub async fn start() -> std::io::Result<Self> {
+        let mock = MockServer::start().await?;
+        let store = Arc::new(Mutex::new(Store::default()));
+
+        for (method, path, handler) in ROUTES {
+            let store = store.clone();
+            mock.on(method, path, move |request| {
+                authenticate(request)
+                    .and_then(|()| handler(&mut lock(&store), request))
+                    .unwrap_or_else(|error| error)
+            });
+        }
+
+        Ok(FakeCloudflare { mock, store })
+    }
+
+    /// The underlying server, to inject failures or inspect requests.
+    pub fn mock(&self) -> &MockServer {
+        &self.mock
+    }
+
+    /// Base URL to point a client at.
+    pub fn url(&self) -> String {
+        self.mock.url()
+    }
+
+    /// A builder for a client of this fake, see [`MockServer::client_builder`].
+    pub fn client_builder(&self) -> ClientBuilder {
+        self.mock.client_builder()
+    }
+
+    /// A client of this fake, see [`MockServer::client`].
+    pub fn client(&self) -> Client {
+        self.mock.client()
+    }
+
+    /// Create an active zone owned by [`FAKE_ACCOUNT_ID`] and return its id.
+    pub fn add_zone(&self, name: &str) -> String {
+        let zone = new_zone(name, FAKE_ACCOUNT_ID);
+        let id = zone["id"].as_str().unwrap_or_default().to_string();
+        lock(&self.store).zones.push(zone);
+        id
+    }
+
+    /// Create a KV namespace owned by [`FAKE_ACCOUNT_ID`] and return its id.
+    pub fn add_namespace(&self, title: &str) -> String {
+        let id = new_id();
+        lock(&self.store).namespaces.push(Namespace {
+            id: id.clone(),
+            account_id: FAKE_ACCOUNT_ID.to_string(),
+            title: title.to_string(),
+            keys: BTreeMap::new(),
+        });
+        id
+    }
+
+    /// Every zone, in creation order.
+    pub fn zones(&self) -> Vec<Value> {
+        lock(&self.store).zones.clone()
+    }
+
+    /// The DNS records of a zone, in creation order.
+    pub fn dns_records(&self, zone_id: &str) -> Vec<Value> {
+        lock(&self.store)
+            .records
+            .get(zone_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// The value stored under `key` in a KV namespace.
+    pub fn kv_value(&self, namespace_id: &str, key: &str) -> Option<Vec<u8>> {
+        let store = lock(&self.store);
+        let namespace = store.namespaces.iter().find(|n| n.id == namespace_id)?;
+        namespace
+            .keys
+            .get(key)
+            .filter(|entry| !entry.expired())
+            .map(|entry| entry.value.clone())
+    }
+}
+
+/// Require an API token or a global API key, as Cloudflare does.
+fn authenticate(request: &MockRequest) -> Result<(), MockResponse> {
+    let header = |name: &str| {
+        request
+            .headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.trim().is_empty())
+    };
+    let token = header("authorization").and_then(|value| value.strip_prefix("Bearer "));
+    let key = header("x-auth-email").and(header("x-auth-key"));
+    let service_key = header("x-auth-user-service-key");
+
+    if token.is_some() || key.is_some() || service_key.is_some() {
+        Ok(())
+    } else {
+        Err(MockResponse::error(
+            403,
+            AUTHENTICATION_ERROR,
+            "Authentication error",
+        ))
+    }
+}
+
+// Zones
+
+fn zones_list(store: &mut Store, request: &MockRequest) -> Outcome {
+    let name = query(request, "name");
+    let status = request.query_param("status");
+    let account_id = request.query_param("account.id");
+
+    let zones = store
+        .zones
+        .iter()
+        .filter(|zone| name.as_deref().is_none_or(|name| zone["name"] == name))
+        .filter(|zone| status.is_none_or(|status| zone["status"] == status))
+        .filter(|zone| account_id.is_none_or(|id| zone["account"]["id"] == id))
+        .cloned()
+        .collect();
+    Ok(paginate(zones, request))
+}
+
+fn zones_create(store: &mut Store, request: &MockRequest) -> Outcome {
+    let body = json_body(request)?;
+    let name = body["name"].as_str().unwrap_or_default().to_lowercase();
+    if !is_domain(&name, false) {
+        return Err(MockResponse::error(
+            400,
+            INVALID_ZONE_NAME,
+            &format!("{} is not a registered domain", name),
+        ));
+    }
+    if store.zones.iter().any(|zone| zone["name"] == name.as_str()) {
+        return Err(MockResponse::error(
+            400,
+            ZONE_EXISTS,
+            &format!("{} already exists", name),
+        ));
+    }
+
+    let account_id = body["account"]["id"].as_str().unwrap_or(FAKE_ACCOUNT_ID);
+    let mut zone = new_zone(&name, account_id);
+    if let Some(kind) = body.get("type") {
+        zone["type"] = kind.clone();
+    }
+    store.zones.push(zone.clone());
+    Ok(MockResponse::success(zone))
+}
+
+fn zones_get(store: &mut Store, request: &MockRequest) -> Outcome {
+    let zone = store.zone(request)?;
+    Ok(MockResponse::success(zone.clone()))
+}
+
+fn zones_edit(store: &mut Store, request: &MockRequest) -> Outcome {
+    let body = json_body(request)?;
+    let zone = store.zone_mut(request)?;
+    for key in ["paused", "type", "vanity_name_servers"] {
+        if let Some(value) = body.get(key) {
+            zone[key] = value.clone();
+        }
+    }
+    zone["modified_on"] = Value::String(now());
+    Ok(MockResponse::success(zone.clone()))
+}
+
+fn zones_delete(store: &mut Store, request: &MockRequest) -> Outcome {
+    let id = store.zone(request)?["id"].clone();
+    store.zones.retain(|zone| zone["id"] != id);
+    if let Some(id) = id.as_str() {
+        store.records.remove(id);
+    }
+    Ok(MockResponse::success(json!({ "id": id })))
+}
+
+fn new_zone(name: &str, account_id: &str) -> Value {
+    let now = now();
+    json!({
+        "id": new_id(),
+        "name": name,
+        "status": "active",
+        "paused": false,
+        "type": "full",
+        "development_mode": 0,
+        "name_servers": ["ns1.example.com", "ns2.example.com"],
+        "original_name_servers": [],
+        "original_registrar": null,
+        "original_dnshost": null,
+        "vanity_name_servers": [],
+        "created_on": now,
+        "modified_on": now,
+        "activated_on": now,
+        "account": { "id": account_id, "name": "Fake account" },
+        "owner": { "id": null, "type": "user", "email": null },
+        "meta": {
+            "cdn_only": false,
+            "custom_certificate_quota": 0,
+            "dns_only": false,
+            "foundation_dns": false,
+            "page_rule_quota": 3,
+            "phishing_detected": false,
+            "step": 2,
+        },
+        "permissions": ["#zone:read", "#zone:edit"],
+        "plan": { "id": "0feeeeeeeeeeeeeeeeeeeeeeeeeeeeee", "name": "Free Website", "legacy_id": "free" },
+    })
+}
+
+impl Store {
+    fn zone(&self, request: &MockRequest) -> Result<&Value, MockResponse> {
+        let id = &request.params["zone_id"];
+        self.zones
+            .iter()
+            .find(|zone| zone["id"] == id.as_str())
+            .ok_or_else(invalid_zone)
+    }
+
+    fn zone_mut(&mut self, request: &MockRequest) -> Result<&mut Value, MockResponse> {
+        let id = &request.params["zone_id"];
+        self.zones
+            .iter_mut()
+            .find(|zone| zone["id"] == id.as_str())
+            .ok_or_else(invalid_zone)
+    }
+}
+
+fn invalid_zone() -> MockResponse {
+    MockResponse::error(404, INVALID_ZONE, "Invalid zone identifier")
+}
+
+// DNS records
+
+fn dns_records_list(store: &mut Store, request: &MockRequest) -> Outcome {
+    let zone_id = store.zone(request)?["id"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let kind = request.query_param("type");
+    let name = query(request, "name");
+    let content = query(request, "content");
+
+    let records = store
+        .records
+        .get(&zone_id)
+        .into_iter()
+        .flatten()
+        .filter(|record| kind.is_none_or(|kind| record["type"] == kind))
+        .filter(|record| name.as_deref().is_none_or(|name| record["name"] == name))
+        .filter(|record| content.as_deref().is_none_or(|c| record["content"] == c))
+        .cloned()
+        .collect();
+    Ok(paginate(records, request))
+}
+
+fn dns_records_create(store: &mut Store, request: &MockRequest) -> Outcome {
+    let zone = store.zone(request)?.clone();
+    let body = json_body(request)?;
+    let record = validate_record(&zone, &Value::Object(Map::new()), &body)?;
+    check_conflicts(store, &zone, &record, None)?;
+
+    let zone_id = zone["id"].as_str().unwrap_or_default().to_string();
+    store
+        .records
+        .entry(zone_id)
+        .or_default()
+        .push(record.clone());
+    Ok(MockResponse::success(record))
+}
+
+fn dns_records_get(store: &mut Store, request: &MockRequest) -> Outcome {
+    let record = store.record_mut(request)?;
+    Ok(MockResponse::success(record.clone()))
+}
+
+fn dns_records_update(store: &mut Store, request: &MockRequest) -> Outcome {
+    change_record(store, request, false)
+}
+
+fn dns_records_edit(store: &mut Store, request: &MockRequest) -> Outcome {
+    change_record(store, request, true)
+}
+
+fn dns_records_delete(store: &mut Store, request: &MockRequest) -> Outcome {
+    let id = store.record_mut(request)?["id"].clone();
+    for records in store.records.values_mut() {
+        records.retain(|record| record["id"] != id);
+    }
+    Ok(MockResponse::success(json!({ "id": id })))
+}
+
+/// Overwrite (`PUT`) or patch (`PATCH`) a record.
+fn change_record(store: &mut Store, request: &MockRequest, patch: bool) -> Outcome {
+    let zone = store.zone(request)?.clone();
+    let existing = store.record_mut(request)?.clone();
+    let body = json_body(request)?;
+
+    let mut changes = if patch {
+        existing.clone()
+    } else {
+        Value::Object(Map::new())
+    };
+    if let (Some(changes), Some(body)) = (changes.as_object_mut(), body.as_object()) {
+        for (key, value) in body {
+            changes.insert(key.clone(), value.clone());
+        }
+    }
+
+    let mut record = validate_record(&zone, &existing, &changes)?;
+    check_conflicts(store, &zone, &record, existing["id"].as_str())?;
+    record["id"] = existing["id"].clone();
+    record["created_on"] = existing["created_on"].clone();
+
+    *store.record_mut(request)? = record.clone();
+    Ok(MockResponse::success(record))
+}
+
+/// Build a record from a request body, validating it like Cloudflare does.
+fn validate_record(zone: &Value, existing: &Value, body: &Value) -> Result<Value, MockResponse> {
+    let zone_name = zone["name"].as_str().unwrap_or_default();
+    let kind = body["type"].as_str().unwrap_or_default().to_uppercase();
+    if !RECORD_TYPES.contains(&kind.as_str()) {
+        return Err(dns_validation(
+            INVALID_DNS_NAME,
+            "Invalid or unsupported record type.",
+        ));
+    }
+
+    let name = body["name"].as_str().unwrap_or_default().to_lowercase();
+    let name = name.trim_end_matches('.');
+    let name = if name == "@" || name == zone_name {
+        zone_name.to_string()
+    } else if name.ends_with(&format!(".{}", zone_name)) {
+        name.to_string()
+    } else if !name.is_empty() {
+        format!("{}.{}", name, zone_name)
+    } else {
+        return Err(dns_validation(INVALID_DNS_NAME, "DNS name is invalid."));
+    };
+    if !is_domain(&name, true) {
+        return Err(dns_validation(INVALID_DNS_NAME, "DNS name is invalid."));
+    }
+
+    let content = body["content"].as_str().unwrap_or_default().to_string();
+    match kind.as_str() {
+        "A" if content.parse::<Ipv4Addr>().is_err() => {
+            return Err(dns_validation(
+                INVALID_A_CONTENT,
+                "Content for A record must be a valid IPv4 address.",
+            ));
+        }
+        "AAAA" if content.parse::<Ipv6Addr>().is_err() => {
+            return Err(dns_validation(
+                INVALID_AAAA_CONTENT,
+                "Content for AAAA record must be a valid IPv6 address.",
+            ));
+        }
+        _ => {}
+    }
+
+    let ttl = match body.get("ttl") {
+        None | Some(Value::Null) => 1,
+        Some(ttl) => ttl.as_u64().unwrap_or(0),
+    };
+    if ttl != 1 && !(60..=86400).contains(&ttl) {
+        return Err(dns_validation(
+            INVALID_TTL,
+            "Invalid TTL. Must be between 60 and 86400 seconds, or 1 for Automatic.",
+        ));
+    }
+
+    let proxiable = PROXIABLE_TYPES.contains(&kind.as_str());
+    let proxied = body["proxied"].as_bool().unwrap_or(false);
+    if proxied && !proxiable {
+        return Err(dns_validation(
+            NOT_PROXIABLE,
+            &format!("{} records cannot be proxied.", kind),
+        ));
+    }
+
+    let now = now();
+    let mut record = json!({
+        "id": existing.get("id").cloned().unwrap_or_else(|| Value::String(new_id())),
+        "zone_id": zone["id"],
+        "zone_name": zone_name,
+        "name": name,
+        "type": kind,
+        "content": content,
+        "proxiable": proxiable,
+        "proxied": proxied,
+        "ttl": ttl,
+        "comment": body.get("comment").cloned().unwrap_or(Value::Null),
+        "tags": body.get("tags").cloned().unwrap_or_else(|| json!([])),
+        "settings": body.get("settings").cloned().unwrap_or_else(|| json!({})),
+        "meta": {},
+        "created_on": now,
+        "modified_on": now,
+    });
+    for key in ["priority", "data"] {
+        if let Some(value) = body.get(key) {
+            record[key] = value.clone();
+        }
+    }
+    Ok(record)
+}
+
+/// Reject duplicate records and CNAMEs that share a name with other records.
+fn check_conflicts(
+    store: &Store,
+    zone: &Value,
+    record: &Value,
+    ignore_id: Option<&str>,
+) -> Result<(), MockResponse> {
+    let zone_id = zone["id"].as_str().unwrap_or_default();
+    let others = store
+        .records
+        .get(zone_id)
+        .into_iter()
+        .flatten()
+        .filter(|other| other["id"].as_str() != ignore_id);
+
+    for other in others.filter(|other| other["name"] == record["name"]) {
+        if other["type"] == record["type"] && other["content"] == record["content"] {
+            return Err(MockResponse::error(
+                400,
+                RECORD_EXISTS,
+                "Record already exists.",
+            ));
+        }
+        let is_host = |r: &Value| matches!(r["type"].as_str(), Some("A" | "AAAA" | "CNAME"));
+        if (other["type"] == "CNAME" || record["type"] == "CNAME")
+            && is_host(other)
+            && is_host(record)
+        {
+            return Err(MockResponse::error(
+                400,
+                CNAME_CONFLICT,
+                "An A, AAAA, or CNAME record with that host already exists.",
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn dns_validation(code: i64, message: &str) -> MockResponse {
+    MockResponse::json(
+        400,
+        json!({
+            "success": false,
+            "errors": [{
+                "code": DNS_VALIDATION,
+                "message": "DNS Validation Error",
+                "error_chain": [{ "code": code, "message": message }],
+            }],
+            "messages": [],
+            "result": null,
+        }),
+    )
+}
+
+impl Store {
+    fn record_mut(&mut self, request: &MockRequest) -> Result<&mut Value, MockResponse> {
+        let zone_id = request.params["zone_id"].clone();
+        let record_id = &request.params["dns_record_id"];
+        if !self.zones.iter().any(|zone| zone["id"] == zone_id.as_str()) {
+            return Err(invalid_zone());
+        }
+        self.records
+            .get_mut(&zone_id)
+            .into_iter()
+            .flatten()
+            .find(|record| record["id"] == record_id.as_str())
+            .ok_or_else(|| MockResponse::error(404, RECORD_NOT_FOUND, "Record does not exist."))
+    }
+}
+
+// Workers KV
+
+fn namespaces_list(store: &mut Store, request: &MockRequest) -> Outcome {
+    let account_id = &request.params["account_id"];
+    let namespaces = store
+        .namespaces
+        .iter()
+        .filter(|namespace| namespace.account_id == *account_id)
+        .map(Namespace::to_json)
+        .collect();
+    Ok(paginate(namespaces, request))
+}
+
+fn namespaces_create(store: &mut Store, request: &MockRequest) -> Outcome {
+    let body = json_body(request)?;
+    let account_id = request.params["account_id"].clone();
+    let title = body["title"].as_str().unwrap_or_default().to_string();
+    if title.is_empty() {
+        return Err(MockResponse::error(
+            400,
+            MALFORMED_JSON,
+            "title is required",
+        ));
+    }
+    if store
+        .namespaces
+        .iter()
+        .any(|namespace| namespace.account_id == account_id && namespace.title == title)
+    {
+        return Err(MockResponse::error(
+            400,
+            NAMESPACE_EXISTS,
+            "A namespace with this account ID and title already exists.",
+        ));
+    }
+
+    let namespace = Namespace {
+        id: new_id(),
+        account_id,
+        title,
+        keys: BTreeMap::new(),
+    };
+    let result = namespace.to_json();
+    store.namespaces.push(namespace);
+    Ok(MockResponse::success(result))
+}
+
+fn namespaces_delete(store: &mut Store, request: &MockRequest) -> Outcome {
+    let id = store.namespace(request)?.id.clone();
+    store.namespaces.retain(|namespace| namespace.id != id);
+    Ok(MockResponse::success(json!({})))
+}
+
+fn keys_list(store: &mut Store, request: &MockRequest) -> Outcome {
+    let namespace = store.namespace(request)?;
+    let prefix = query(request, "prefix").unwrap_or_default();
+    let limit = request
+        .query_param("limit")
+        .and_then(|limit| limit.parse::<usize>().ok())
+        .unwrap_or(1000)
+        .clamp(10, 1000);
+    let start = request
+        .query_param("cursor")
+        .and_then(|cursor| cursor.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    let keys: Vec<Value> = namespace
+        .keys
+        .iter()
+        .filter(|(name, entry)| name.starts_with(&prefix) && !entry.expired())
+        .map(|(name, entry)| {
+            let mut key = json!({ "name": name });
+            if let Some(expiration) = entry.expiration {
+                key["expiration"] = json!(expiration);
+            }
+            if let Some(metadata) = &entry.metadata {
+                key["metadata"] = metadata.clone();
+            }
+            key
+        })
+        .collect();
+
+    let page: Vec<Value> = keys.iter().skip(start).take(limit).cloned().collect();
+    let end = start + page.len();
+    let cursor = if end < keys.len() {
+        end.to_string()
+    } else {
+        String::new()
+    };
+    Ok(MockResponse::json(
+        200,
+        json!({
+            "success": true,
+            "errors": [],
+            "messages": [],
+            "result": page,
+            "result_info": { "count": page.len(), "cursor": cursor },
+        }),
+    ))
+}
+
+fn values_get(store: &mut Store, request: &MockRequest) -> Outcome {
+    let entry = store.entry(request)?;
+    Ok(MockResponse::raw(200, entry.value.clone())
+        .header("content-type", "application/octet-stream"))
+}
+
+fn values_put(store: &mut Store, request: &MockRequest) -> Outcome {
+    let key = percent_decode(&request.params["key_name"]);
+    let now = unix_time();
+    let expiration = match (
+        request
+            .query_param("expiration")
+            .and_then(|e| e.parse::<u64>().ok()),
+        request
+            .query_param("expiration_ttl")
+            .and_then(|t| t.parse::<u64>().ok()),
+    ) {
+        (_, Some(ttl)) => Some(now + ttl),
+        (expiration, None) => expiration,
+    };
+    let metadata = request
+        .headers
+        .get("cf-kv-metadata")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| serde_json::from_str(value).ok());
+
+    let namespace = store.namespace_mut(request)?;
+    namespace.keys.insert(
+        key,
+        Entry {
+            value: request.body.clone(),
+            metadata,
+            expiration,
+        },
+    );
+    Ok(MockResponse::success(json!({})))
+}
+
+fn values_delete(store: &mut Store, request: &MockRequest) -> Outcome {
+    let key = percent_decode(&request.params["key_name"]);
+    store.namespace_mut(request)?.keys.remove(&key);
+    Ok(MockResponse::success(json!({})))
+}
+
+fn metadata_get(store: &mut Store, request: &MockRequest) -> Outcome {
+    let entry = store.entry(request)?;
+    Ok(MockResponse::success(
+        entry.metadata.clone().unwrap_or(Value::Null),
+    ))
+}
+
+impl Store {
+    fn namespace(&self, request: &MockRequest) -> Result<&Namespace, MockResponse> {
+        let account_id = &request.params["account_id"];
+        let id = &request.params["namespace_id"];
+        self.namespaces
+            .iter()
+            .find(|namespace| namespace.id == *id && namespace.account_id == *account_id)
+            .ok_or_else(namespace_not_found)
+    }
+
+    fn namespace_mut(&mut self, request: &MockRequest) -> Result<&mut Namespace, MockResponse> {
+        let account_id = &request.params["account_id"];
+        let id = &request.params["namespace_id"];
+        self.namespaces
+            .iter_mut()
+            .find(|namespace| namespace.id == *id && namespace.account_id == *account_id)
+            .ok_or_else(namespace_not_found)
+    }
+
+    fn entry(&self, request: &MockRequest) -> Result<&Entry, MockResponse> {
+        let key = percent_decode(&request.params["key_name"]);
+        self.namespace(request)?
+            .keys
+            .get(&key)
+            .filter(|entry| !entry.expired())
+            .ok_or_else(|| MockResponse::error(404, KEY_NOT_FOUND, "get: 'key not found'"))
+    }
+}
+
+impl Namespace {
+    fn to_json(&self) -> Value {
+        json!({ "id": self.id, "title": self.title, "supports_url_encoding": true })
+    }
+}
+
+impl Entry {
+    fn expired(&self) -> bool {
+        self.expiration
+            .is_some_and(|expiration| expiration <= unix_time())
+    }
+}
+
+fn namespace_not_found() -> MockResponse {
+    MockResponse::error(404, NAMESPACE_NOT_FOUND, "namespace not found")
+}
+
+// Helpers
+
+fn json_body(request: &MockRequest) -> Result<Value, MockResponse> {
+    request
+        .json::<Value>()
+        .ok()
+        .filter(Value::is_object)
+        .ok_or_else(|| MockResponse::error(400, MALFORMED_JSON, "Malformed JSON in request body"))
+}
+
+/// A page of `items` per the `page` and `per_page` query parameters.
+fn paginate(items: Vec<Value>, request: &MockRequest) -> MockResponse {
+    let page = request
+        .query_param("page")
+        .and_then(|page| page.parse::<usize>().ok())
+        .unwrap_or(1)
+        .max(1);
+    let per_page = request
+        .query_param("per_page")
+        .and_then(|per_page| per_page.parse::<usize>().ok())
+        .unwrap_or(20)
+        .clamp(5, 1000);
+
+    let total_count = items.len();
+    let result: Vec<Value> = items
+        .into_iter()
+        .skip((page - 1) * per_page)
+        .take(per_page)
+        .collect();
+    MockResponse::json(
+        200,
+        json!({
+            "success": true,
+            "errors": [],
+            "messages": [],
+            "result_info": {
+                "page": page,
+                "per_page": per_page,
+                "count": result.len(),
+                "total_count": total_count,
+                "total_pages": total_count.div_ceil(per_page),
+            },
+            "result": result,
+        }),
+    )
+}
+
+/// Whether `name` looks like a domain: at least two dot-separated labels of
+/// letters, digits and inner hyphens. Record names may also use underscores
+/// (`_dmarc`) and a leading wildcard (`*.example.com`).
+fn is_domain(name: &str, record: bool) -> bool {
+    let labels: Vec<&str> = name.split('.').collect();
+    labels.len() >= 2
+        && name.len() <= 253
+        && labels.iter().enumerate().all(|(i, label)| {
+            (record && i == 0 && *label == "*")
+                || (!label.is_empty()
+                    && label.len() <= 63
+                    && !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || (record && c == '_')))
+        })
+}
+
+/// The decoded value of a query parameter.
+fn query(request: &MockRequest, name: &str) -> Option<String> {
+    request
+        .query_param(name)
+        .map(|value| percent_decode(&value.replace('+', " ")))
+}
+
+/// Decode `%XX` escapes.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => match u8::from_str_radix(&value[i + 1..i + 3], 16) {
+                Ok(byte) => {
+                    decoded.push(byte);
+                    i += 3;
+                    continue;
+                }
+                Err(_) => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn new_id() -> String {
+    format!("{:032x}", fastrand::u128(..))
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// The current time as RFC 3339, e.g. `2014-01-01T05:20:00Z`.
+fn now() -> String {
+    let secs = unix_time();
+    let (days, time) = (secs / 86400, secs % 86400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
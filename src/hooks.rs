@@ -0,0 +1,195 @@
+//! State and hooks shared with the generated client.
+//!
+//! The build script generates `Client` with [`ClientState`] as its inner
+//! type, calls these hooks around every request, sends requests with
+//! [`execute`] and reads their responses with [`from_response`].
+
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use progenitor_client::{Error, ResponseValue};
+use reqwest::header::{Entry, HeaderMap};
+use serde::de::DeserializeOwned;
+
+use crate::transport::{BackendFuture, HttpBackend};
+use crate::{RateLimiter, RetryPolicy, SchemaDrift, drift, time};
+
+/// Per-client configuration shared by every clone of a [`Client`](crate::Client).
+#[derive(Clone, Debug, Default)]
+pub struct ClientState {
+    retry: Arc<RetryPolicy>,
+    rate_limiter: Option<RateLimiter>,
+    timeout: Option<Duration>,
+    backend: Option<Backend>,
+    schema_drift: SchemaDrift,
+}
+
+/// A custom backend, and the headers the `reqwest::Client` would have added.
+#[derive(Clone, Debug)]
+struct Backend {
+    backend: Arc<dyn HttpBackend>,
+    headers: HeaderMap,
+}
+
+impl ClientState {
+    /// Create the state for a client that retries with `retry`.
+    pub fn new(retry: RetryPolicy) -> Self {
+        ClientState {
+            retry: Arc::new(retry),
+            rate_limiter: None,
+            timeout: None,
+            backend: None,
+            schema_drift: SchemaDrift::Error,
+        }
+    }
+
+    /// Make every request wait on `rate_limiter` before it is sent.
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Give every request that has no timeout of its own `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Send requests with `backend` instead of the client's
+    /// `reqwest::Client`, adding `headers` (credentials, `User-Agent`) unless
+    /// a request sets them itself.
+    pub fn with_backend(mut self, backend: Arc<dyn HttpBackend>, headers: HeaderMap) -> Self {
+        self.backend = Some(Backend { backend, headers });
+        self
+    }
+
+    /// Handle responses that do not match the schema according to `policy`.
+    pub fn with_schema_drift(mut self, policy: SchemaDrift) -> Self {
+        self.schema_drift = policy;
+        self
+    }
+
+    /// The retry policy requests are sent again with.
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    /// The rate limiter requests wait on, if any.
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_ref()
+    }
+
+    /// The timeout of requests without one of their own, if any.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// The backend requests are sent with, if not the client's
+    /// `reqwest::Client`.
+    pub fn backend(&self) -> Option<&dyn HttpBackend> {
+        self.backend.as_ref().map(|backend| &*backend.backend)
+    }
+
+    /// How responses that do not match the schema are handled.
+    pub fn schema_drift(&self) -> SchemaDrift {
+        self.schema_drift
+    }
+}
+
+/// Called by the generated client before each request is sent.
+pub(crate) async fn pre_request(
+    state: &ClientState,
+    request: &mut reqwest::Request,
+) -> Result<(), Infallible> {
+    if let Some(timeout) = state.timeout
+        && request.timeout().is_none()
+    {
+        *request.timeout_mut() = Some(timeout);
+    }
+
+    if let Some(rate_limiter) = &state.rate_limiter {
+        rate_limiter.acquire().await;
+    }
+
+    Ok(())
+}
+
+/// Called by the generated client to send each request, in place of
+/// `reqwest::Client::execute`.
+///
+/// Sends the request again while the retry policy says so, waiting on the
+/// rate limiter before each retry. Requests with a streaming body cannot be
+/// cloned and are sent once.
+pub(crate) fn execute<'a>(
+    state: &'a ClientState,
+    client: &'a reqwest::Client,
+    mut request: reqwest::Request,
+) -> BackendFuture<'a> {
+    if let Some(Backend { headers, .. }) = &state.backend {
+        for (name, value) in headers {
+            if let Entry::Vacant(entry) = request.headers_mut().entry(name) {
+                entry.insert(value.clone());
+            }
+        }
+    }
+
+    Box::pin(async move {
+        let mut attempt = 0;
+        loop {
+            let retry = state
+                .retry
+                .applies_to(request.method())
+                .then(|| request.try_clone())
+                .flatten();
+            let result = send(state, client, request).await;
+            let Some(next) = retry else {
+                return result;
+            };
+
+            let (result, delay) = state.retry.delay(attempt, result).await;
+            let Some(delay) = delay else {
+                return result;
+            };
+            // The generated client only hands the final one to `post_response`
+            post_response(state, &result);
+            time::sleep(delay).await;
+            if let Some(rate_limiter) = &state.rate_limiter {
+                rate_limiter.acquire().await;
+            }
+
+            request = next;
+            attempt += 1;
+        }
+    })
+}
+
+fn send<'a>(
+    state: &'a ClientState,
+    client: &'a reqwest::Client,
+    request: reqwest::Request,
+) -> BackendFuture<'a> {
+    match &state.backend {
+        Some(Backend { backend, .. }) => backend.execute(request),
+        None => HttpBackend::execute(client, request),
+    }
+}
+
+/// Called by the generated client to deserialize each JSON response, in
+/// place of `ResponseValue::from_response`.
+pub(crate) async fn from_response<T: DeserializeOwned, E>(
+    state: &ClientState,
+    response: reqwest::Response,
+) -> Result<ResponseValue<T>, Error<E>> {
+    drift::from_response(state.schema_drift, response).await
+}
+
+/// Called by the generated client after each response is received.
+pub(crate) fn post_response(
+    state: &ClientState,
+    result: &Result<reqwest::Response, reqwest::Error>,
+) {
+    if let (Some(rate_limiter), Ok(response)) = (&state.rate_limiter, result) {
+        rate_limiter.observe(response.headers());
+    }
+}
@@ -1,5 +1,43 @@
 // Include the generated API client code
 include!(concat!(env!("OUT_DIR"), "/cloudflare_api.rs"));
 
+// Include the per-product modules (`dns`, `workers`, `zero_trust::access`, ...)
+// that re-export each tag's client extension trait and hold its types
+include!(concat!(env!("OUT_DIR"), "/modules.rs"));
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "cassette")]
+pub mod cassette;
+mod client_builder;
+mod drift;
+mod envelope;
+mod error;
+#[cfg(feature = "mock")]
+pub mod fake;
+mod hooks;
+#[cfg(feature = "mock")]
+pub mod mock;
+mod pagination;
+mod rate_limit;
+mod retry;
+mod time;
+mod transport;
+
+pub use client_builder::{BuildError, ClientBuilder, Credentials, DEFAULT_BASE_URL, Secret};
+pub use drift::SchemaDrift;
+
+pub use envelope::{
+    ApiResponse, Cursors, Envelope, MessageSource, ResponseExt, ResponseMessage, ResultInfo,
+};
+pub use error::{ApiError, CloudflareError};
+pub use hooks::ClientState;
+pub use pagination::{
+    DEFAULT_PER_PAGE, PAGINATED_OPERATIONS, PageRequest, PaginationStyle, Paginator,
+};
+pub use rate_limit::{CLOUDFLARE_DEFAULT_LIMIT, RateLimiter};
+pub use retry::{RetryPolicy, TRANSIENT_CODES};
+pub use transport::{BackendFuture, HttpBackend};
+
 // Re-export commonly used types
 pub use progenitor_client::{ByteStream, Error, ResponseValue};
@@ -0,0 +1,447 @@
+//! A local Cloudflare API for tests (`mock` feature).
+//!
+//! [`MockServer`] serves every operation of the generated client with a
+//! canned response derived from the same patched spec: the spec's example,
+//! or a value synthesized from the response schema, in the Cloudflare
+//! envelope. Tests can register their own handlers, inject failures and
+//! inspect the requests the server received:
+//!
+//! ```ignore
+//! use cloudflare_api::RetryPolicy;
+//! use cloudflare_api::mock::{MockResponse, MockServer};
+//!
+//! let mock = MockServer::start().await?;
+//! mock.on("GET", "/zones/{zone_id}", |request| {
+//!     MockResponse::success(serde_json::json!({ "id": request.params["zone_id"] }))
+//! });
+//! mock.fail_next(1, MockResponse::rate_limited(1));
+//!
+//! let client = mock.client_builder().retry_policy(RetryPolicy::default()).build()?;
+//! let zone: serde_json::Value = client
+//!     .zones_get()
+//!     .zone_id("023e105f4ecef8ad9ca31a8372d0c353")
+//!     .send()
+//!     .await
+//!     .into_result()?;
+//!
+//! mock.assert_requested("GET", "/zones/{zone_id}", 2);
+//! ```
+//!
+//! [`MockServer::client`] gives a client that talks to the server with
+//! [`MOCK_API_TOKEN`], and [`MockServer::client_builder`] one to configure
+//! further; any other client only needs [`MockServer::url`] as its base URL.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::Infallible;
+use std::fmt;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use reqwest::header::HeaderMap;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+use crate::{Client, ClientBuilder, RetryPolicy};
+
+include!(concat!(env!("OUT_DIR"), "/mock_routes.rs"));
+
+/// API token the clients from [`MockServer::client`] send.
+pub const MOCK_API_TOKEN: &str = "mock-api-token";
+
+/// Cloudflare's error code for a request that matches no route.
+const NO_ROUTE_CODE: i64 = 7003;
+
+/// How long the server waits before accepting again after a failed accept.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(50);
+
+type Handler = Arc<dyn Fn(&MockRequest) -> MockResponse + Send + Sync>;
+
+/// A local HTTP server that answers like the Cloudflare API.
+///
+/// The server runs on the current tokio runtime and stops when dropped.
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<State>,
+    task: JoinHandle<()>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Registered handlers, most recent last.
+    handlers: Mutex<Vec<(String, String, Handler)>>,
+    /// Responses served before any route, oldest first.
+    faults: Mutex<VecDeque<MockResponse>>,
+    requests: Mutex<Vec<MockRequest>>,
+}
+
+impl MockServer {
+    /// Start a server on a free localhost port.
+    pub async fn start() -> std::io::Result<Self> {
+        let state = Arc::new(State::default());
+        let (addr, task) = serve({
+            let state = state.clone();
+            move |mut request| {
+                let state = state.clone();
+                async move {
+                    let response = state.respond(&mut request);
+                    lock(&state.requests).push(request);
+                    response
+                }
+            }
+        })
+        .await?;
+
+        Ok(MockServer { addr, state, task })
+    }
+
+    /// Base URL to point a client at, e.g. `http://127.0.0.1:41237`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// A builder for a client of this server, authenticated with
+    /// [`MOCK_API_TOKEN`] and without retries.
+    pub fn client_builder(&self) -> ClientBuilder {
+        Client::builder()
+            .base_url(self.url())
+            .api_token(MOCK_API_TOKEN)
+            .retry_policy(RetryPolicy::none())
+    }
+
+    /// A client of this server, see [`MockServer::client_builder`].
+    pub fn client(&self) -> Client {
+        self.client_builder()
+            .build()
+            .expect("a mock client needs no configuration that can fail")
+    }
+
+    /// Answer requests matching `method` and `path` with `handler` instead of
+    /// the canned response.
+    ///
+    /// `path` is a template as in the spec (`/zones/{zone_id}`); the values
+    /// of its parameters are in [`MockRequest::params`]. Handlers registered
+    /// later take precedence.
+    pub fn on<F>(&self, method: &str, path: &str, handler: F)
+    where
+        F: Fn(&MockRequest) -> MockResponse + Send + Sync + 'static,
+    {
+        lock(&self.state.handlers).push((
+            method.to_uppercase(),
+            path.to_string(),
+            Arc::new(handler),
+        ));
+    }
+
+    /// Answer the next `times` requests, whatever their route, with `response`.
+    ///
+    /// Use [`MockResponse::rate_limited`] or [`MockResponse::error`] to test
+    /// retries and error handling.
+    pub fn fail_next(&self, times: usize, response: MockResponse) {
+        let mut faults = lock(&self.state.faults);
+        faults.extend(std::iter::repeat_n(response, times));
+    }
+
+    /// Every request received so far, oldest first.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        lock(&self.state.requests).clone()
+    }
+
+    /// The requests received so far that match `method` and the `path` template.
+    pub fn requests_to(&self, method: &str, path: &str) -> Vec<MockRequest> {
+        lock(&self.state.requests)
+            .iter()
+            .filter(|request| {
+                request.method.eq_ignore_ascii_case(method)
+                    && match_path(path, &request.path).is_some()
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Panic unless exactly `times` requests matched `method` and the `path` template.
+    #[track_caller]
+    pub fn assert_requested(&self, method: &str, path: &str, times: usize) {
+        let matched = self.requests_to(method, path).len();
+        if matched != times {
+            let received: Vec<String> = self
+                .requests()
+                .iter()
+                .map(|request| format!("{} {}", request.method, request.path))
+                .collect();
+            panic!(
+                "expected {} {} {} time(s), got {}; received: {:#?}",
+                method, path, times, matched, received
+            );
+        }
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl fmt::Debug for MockServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockServer")
+            .field("url", &self.url())
+            .finish_non_exhaustive()
+    }
+}
+
+impl State {
+    fn respond(&self, request: &mut MockRequest) -> MockResponse {
+        if let Some(response) = lock(&self.faults).pop_front() {
+            return response;
+        }
+
+        let handler = lock(&self.handlers)
+            .iter()
+            .rev()
+            .filter(|(method, _, _)| *method == request.method)
+            .find_map(|(_, path, handler)| {
+                Some((match_path(path, &request.path)?, handler.clone()))
+            });
+        if let Some((params, handler)) = handler {
+            request.params = params;
+            return handler(request);
+        }
+
+        // The most specific template wins, e.g. `/dns_records/export` over `/dns_records/{id}`
+        let canned = MOCK_ROUTES
+            .iter()
+            .filter(|(method, _, _, _)| *method == request.method)
+            .filter_map(|(_, path, status, body)| {
+                let params = match_path(path, &request.path)?;
+                Some((
+                    path.split('/').filter(|s| !s.starts_with('{')).count(),
+                    params,
+                    status,
+                    body,
+                ))
+            })
+            .max_by_key(|(literals, _, _, _)| *literals);
+        match canned {
+            Some((_, params, status, body)) => {
+                request.params = params;
+                MockResponse::raw(*status, body.as_bytes().to_vec())
+            }
+            None => MockResponse::error(
+                404,
+                NO_ROUTE_CODE,
+                &format!("Could not route to {}", request.path),
+            ),
+        }
+    }
+}
+
+/// A request received by a [`MockServer`].
+#[derive(Clone, Debug)]
+pub struct MockRequest {
+    /// Upper-case HTTP method.
+    pub method: String,
+    /// Path without the query string.
+    pub path: String,
+    /// Raw query string, if any.
+    pub query: Option<String>,
+    /// Request headers, e.g. `authorization`.
+    pub headers: HeaderMap,
+    /// Raw request body, empty for requests without one.
+    pub body: Vec<u8>,
+    /// Values of the path template's parameters, e.g. `zone_id`.
+    pub params: BTreeMap<String, String>,
+}
+
+impl MockRequest {
+    async fn from_hyper(request: hyper::Request<Incoming>) -> Self {
+        let (parts, body) = request.into_parts();
+        let body = match body.collect().await {
+            Ok(body) => body.to_bytes().to_vec(),
+            Err(_) => Vec::new(),
+        };
+        MockRequest {
+            method: parts.method.as_str().to_string(),
+            path: parts.uri.path().to_string(),
+            query: parts.uri.query().map(str::to_string),
+            headers: parts.headers,
+            body,
+            params: BTreeMap::new(),
+        }
+    }
+
+    /// Deserialize the JSON body.
+    pub fn json<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_slice(&self.body)
+    }
+
+    /// The first value of a query parameter, undecoded.
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .as_deref()?
+            .split('&')
+            .filter_map(|pair| pair.split_once('=').or(Some((pair, ""))))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+}
+
+/// A response for a [`MockServer`] to send.
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    pub(crate) status: u16,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: Vec<u8>,
+}
+
+impl MockResponse {
+    /// `200 OK` with `result` in a successful envelope.
+    pub fn success(result: impl Serialize) -> Self {
+        Self::json(
+            200,
+            json!({ "success": true, "errors": [], "messages": [], "result": result }),
+        )
+    }
+
+    /// A failed envelope with one error.
+    pub fn error(status: u16, code: i64, message: &str) -> Self {
+        Self::json(
+            status,
+            json!({
+                "success": false,
+                "errors": [{ "code": code, "message": message }],
+                "messages": [],
+                "result": null,
+            }),
+        )
+    }
+
+    /// `429 Too Many Requests` with a `Retry-After` of `retry_after` seconds,
+    /// as Cloudflare sends when the rate limit is exceeded.
+    pub fn rate_limited(retry_after: u64) -> Self {
+        Self::error(429, 10013, "Rate limited. Please wait and try again.")
+            .header("retry-after", &retry_after.to_string())
+    }
+
+    /// Any JSON body.
+    pub fn json(status: u16, body: impl Serialize) -> Self {
+        let body = serde_json::to_vec(&body).expect("a mock response body must serialize");
+        Self::raw(status, body)
+    }
+
+    /// Any body, sent as `application/json` unless empty or another
+    /// `content-type` header is added.
+    pub fn raw(status: u16, body: Vec<u8>) -> Self {
+        MockResponse {
+            status,
+            headers: Vec::new(),
+            body,
+        }
+    }
+
+    /// Add a header.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn into_hyper(self) -> hyper::Response<Full<Bytes>> {
+        let mut builder = hyper::Response::builder().status(self.status);
+        let has_content_type = self
+            .headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("content-type"));
+        if !self.body.is_empty() && !has_content_type {
+            builder = builder.header("content-type", "application/json");
+        }
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        builder
+            .body(Full::new(Bytes::from(self.body)))
+            .unwrap_or_else(|e| {
+                let mut response = hyper::Response::new(Full::new(Bytes::from(e.to_string())));
+                *response.status_mut() = hyper::StatusCode::INTERNAL_SERVER_ERROR;
+                response
+            })
+    }
+}
+
+/// Serve `handler` on a free localhost port until the returned task is aborted.
+pub(crate) async fn serve<H, F>(handler: H) -> std::io::Result<(SocketAddr, JoinHandle<()>)>
+where
+    H: Fn(MockRequest) -> F + Clone + Send + Sync + 'static,
+    F: Future<Output = MockResponse> + Send,
+{
+    let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+    let addr = listener.local_addr()?;
+
+    let task = tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                // Running out of file descriptors, say; retrying right away
+                // would spin until the error clears
+                Err(_) => {
+                    tokio::time::sleep(ACCEPT_BACKOFF).await;
+                    continue;
+                }
+            };
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |request| {
+                    let handler = handler.clone();
+                    async move {
+                        let request = MockRequest::from_hyper(request).await;
+                        Ok::<_, Infallible>(handler(request).await.into_hyper())
+                    }
+                });
+                // A client hanging up mid-request is not an error for the test
+                let _ = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await;
+            });
+        }
+    });
+
+    Ok((addr, task))
+}
+
+/// Match a path against a template, returning the template's parameters.
+fn match_path(template: &str, path: &str) -> Option<BTreeMap<String, String>> {
+    let template: Vec<&str> = template.trim_end_matches('/').split('/').collect();
+    let path: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    if template.len() != path.len() {
+        return None;
+    }
+
+    let mut params = BTreeMap::new();
+    for (expected, actual) in template.iter().zip(&path) {
+        match expected.strip_prefix('{').and_then(|p| p.strip_suffix('}')) {
+            Some(_) if actual.is_empty() => return None,
+            Some(name) => {
+                params.insert(name.to_string(), actual.to_string());
+            }
+            None if expected != actual => return None,
+            None => {}
+        }
+    }
+    Some(params)
+}
+
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    // A panicking handler must not take the other tests down with it
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,261 @@
+//! Automatic pagination of list operations.
+//!
+//! Cloudflare list endpoints are paginated either by page number
+//! (`page`/`per_page`, with `result_info.total_pages`) or by cursor
+//! (`cursor`, with `result_info.cursors.after`). A [`Paginator`] drives a
+//! generated operation page by page and yields the individual items:
+//!
+//! ```ignore
+//! use cloudflare_api::prelude::*;
+//! use cloudflare_api::{PaginationStyle, Paginator};
+//!
+//! let records: Vec<serde_json::Value> = Paginator::new(PaginationStyle::Page, |page| {
+//!     client
+//!         .dns_records_list()
+//!         .zone_id(zone_id)
+//!         .page(page.page.unwrap_or(1) as f64)
+//!         .per_page(page.per_page as f64)
+//!         .send()
+//! })
+//! .per_page(100)
+//! .concurrency(4)
+//! .collect_all()
+//! .await?;
+//! ```
+
+use std::future::Future;
+
+use futures::stream::{self, Stream, TryStreamExt};
+use serde::de::DeserializeOwned;
+
+use crate::{ApiResponse, CloudflareError, ResponseExt, ResultInfo};
+
+// Generated from the schema's query parameters
+include!(concat!(env!("OUT_DIR"), "/pagination.rs"));
+
+/// Default number of items requested per page.
+pub const DEFAULT_PER_PAGE: u64 = 50;
+
+/// How a list operation is paginated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PaginationStyle {
+    /// `page` and `per_page` query parameters.
+    Page,
+    /// `cursor` query parameter.
+    Cursor,
+}
+
+impl PaginationStyle {
+    /// The pagination style of a generated method, by name.
+    pub fn of(method: &str) -> Option<PaginationStyle> {
+        PAGINATED_OPERATIONS
+            .binary_search_by(|(name, _)| (*name).cmp(method))
+            .ok()
+            .map(|i| PAGINATED_OPERATIONS[i].1)
+    }
+}
+
+/// The page a [`Paginator`] asks the operation to fetch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PageRequest {
+    /// Page number, starting at 1 (page pagination).
+    pub page: Option<u64>,
+    /// Number of items per page.
+    pub per_page: u64,
+    /// Cursor returned by the previous page (cursor pagination).
+    pub cursor: Option<String>,
+}
+
+/// Streams every item of a paginated list operation.
+pub struct Paginator<F> {
+    fetch: F,
+    style: PaginationStyle,
+    per_page: u64,
+    concurrency: usize,
+}
+
+impl<F, Fut> Paginator<F>
+where
+    F: FnMut(PageRequest) -> Fut,
+    Fut: Future,
+    Fut::Output: ResponseExt,
+{
+    /// Paginate the operation called by `fetch`.
+    pub fn new(style: PaginationStyle, fetch: F) -> Self {
+        Paginator {
+            fetch,
+            style,
+            per_page: DEFAULT_PER_PAGE,
+            concurrency: 1,
+        }
+    }
+
+    /// Paginate a generated method, looking its style up by name.
+    ///
+    /// Returns `None` if the method is not paginated.
+    pub fn for_method(method: &str, fetch: F) -> Option<Self> {
+        PaginationStyle::of(method).map(|style| Self::new(style, fetch))
+    }
+
+    /// Number of items requested per page.
+    pub fn per_page(mut self, per_page: u64) -> Self {
+        self.per_page = per_page.max(1);
+        self
+    }
+
+    /// Maximum number of pages fetched at once.
+    ///
+    /// Only page pagination can fetch concurrently; cursors must be followed
+    /// one page at a time.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Stream every item of every page.
+    pub fn into_stream<T>(self) -> impl Stream<Item = Result<T, CloudflareError>>
+    where
+        T: DeserializeOwned,
+    {
+        let next = match self.style {
+            PaginationStyle::Page => Next::Page {
+                page: 1,
+                total_pages: None,
+            },
+            PaginationStyle::Cursor => Next::Cursor(None),
+        };
+        let state = State {
+            fetch: self.fetch,
+            per_page: self.per_page,
+            concurrency: self.concurrency,
+            next,
+        };
+
+        stream::try_unfold(state, State::next_pages)
+            .map_ok(|items| stream::iter(items.into_iter().map(Ok)))
+            .try_flatten()
+    }
+
+    /// Fetch every page and collect all items.
+    pub async fn collect_all<T>(self) -> Result<Vec<T>, CloudflareError>
+    where
+        T: DeserializeOwned,
+    {
+        self.into_stream().try_collect().await
+    }
+}
+
+/// The number of pages from `result_info.total_count`, with the page size the
+/// server reports, or else the size of a page it returned.
+fn pages_from_count(info: &ResultInfo, len: u64) -> Option<u64> {
+    let per_page = info
+        .per_page
+        .filter(|per_page| *per_page > 0)
+        .unwrap_or(len);
+    match info.total_count? {
+        0 => Some(0),
+        total_count => (per_page > 0).then(|| total_count.div_ceil(per_page)),
+    }
+}
+
+enum Next {
+    Page { page: u64, total_pages: Option<u64> },
+    Cursor(Option<String>),
+    Done,
+}
+
+struct State<F> {
+    fetch: F,
+    per_page: u64,
+    concurrency: usize,
+    next: Next,
+}
+
+impl<F, Fut> State<F>
+where
+    F: FnMut(PageRequest) -> Fut,
+    Fut: Future,
+    Fut::Output: ResponseExt,
+{
+    /// Fetch the next batch of pages, returning their items.
+    async fn next_pages<T>(mut self) -> Result<Option<(Vec<T>, Self)>, CloudflareError>
+    where
+        T: DeserializeOwned,
+    {
+        match self.next {
+            Next::Done => Ok(None),
+            Next::Page { page, total_pages } => {
+                // The first page tells us how many pages there are
+                let last = match total_pages {
+                    Some(total) => total.min(page + self.concurrency as u64 - 1),
+                    None => page,
+                };
+
+                let requests: Vec<_> = (page..=last)
+                    .map(|page| {
+                        (self.fetch)(PageRequest {
+                            page: Some(page),
+                            per_page: self.per_page,
+                            cursor: None,
+                        })
+                    })
+                    .collect();
+                let responses = futures::future::join_all(requests).await;
+
+                let mut items = Vec::new();
+                let mut total_pages = total_pages;
+                let mut exhausted = false;
+                for response in responses {
+                    let response: ApiResponse<Vec<T>> = response.into_api_response()?;
+                    let info = response.result_info.unwrap_or_default();
+                    let len = response.result.len() as u64;
+                    total_pages = total_pages
+                        .or(info.total_pages)
+                        .or_else(|| pages_from_count(&info, len));
+                    // Servers cap `per_page`, so without totals only a page
+                    // shorter than the size they report, or an empty one, is
+                    // known to be the last
+                    exhausted |= len == 0 || info.per_page.is_some_and(|per_page| len < per_page);
+                    items.extend(response.result);
+                }
+
+                self.next = match total_pages {
+                    Some(total) if last < total => Next::Page {
+                        page: last + 1,
+                        total_pages,
+                    },
+                    None if !exhausted => Next::Page {
+                        page: last + 1,
+                        total_pages,
+                    },
+                    _ => Next::Done,
+                };
+
+                Ok(Some((items, self)))
+            }
+            Next::Cursor(cursor) => {
+                let response: ApiResponse<Vec<T>> = (self.fetch)(PageRequest {
+                    page: None,
+                    per_page: self.per_page,
+                    cursor,
+                })
+                .await
+                .into_api_response()?;
+
+                let info = response.result_info.unwrap_or_default();
+                let after = info
+                    .cursors
+                    .and_then(|c| c.after)
+                    .or(info.cursor)
+                    .filter(|c| !c.is_empty());
+
+                self.next = match after {
+                    Some(after) if !response.result.is_empty() => Next::Cursor(Some(after)),
+                    _ => Next::Done,
+                };
+
+                Ok(Some((response.result, self)))
+            }
+        }
+    }
+}
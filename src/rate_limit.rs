@@ -0,0 +1,172 @@
+//! Client-side rate limiting.
+//!
+//! A [`RateLimiter`] is a token bucket that every request waits on before it
+//! is sent. It is shared by all clones of a client (and may be shared between
+//! clients using the same account), and adjusts itself from Cloudflare's
+//! `Ratelimit` and `Ratelimit-Policy` response headers.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use reqwest::header::HeaderMap;
+
+use crate::time::{self, Instant};
+
+/// Cloudflare's default account-wide limit: 1200 requests per 5 minutes.
+pub const CLOUDFLARE_DEFAULT_LIMIT: (u32, Duration) = (1200, Duration::from_secs(300));
+
+/// A token bucket shared by every clone.
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    /// Tokens added per second.
+    rate: f64,
+    /// Time to refill the whole bucket, the longest a token takes.
+    window: Duration,
+    tokens: f64,
+    last_refill: Instant,
+    /// Set when the server reported no remaining budget.
+    blocked_until: Option<Instant>,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_refill = now;
+    }
+}
+
+impl RateLimiter {
+    /// Allow `requests` requests per `window`, starting with a full bucket.
+    pub fn new(requests: u32, window: Duration) -> Self {
+        let capacity = f64::from(requests.max(1));
+
+        RateLimiter {
+            bucket: Arc::new(Mutex::new(Bucket {
+                capacity,
+                rate: capacity / window.as_secs_f64().max(f64::EPSILON),
+                window,
+                tokens: capacity,
+                last_refill: Instant::now(),
+                blocked_until: None,
+            })),
+        }
+    }
+
+    /// Cloudflare's default limit, see [`CLOUDFLARE_DEFAULT_LIMIT`].
+    pub fn cloudflare_default() -> Self {
+        let (requests, window) = CLOUDFLARE_DEFAULT_LIMIT;
+        Self::new(requests, window)
+    }
+
+    /// Wait until a request may be sent, and take a token for it.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+                let now = Instant::now();
+
+                match bucket.blocked_until {
+                    Some(until) if until > now => until - now,
+                    _ => {
+                        bucket.blocked_until = None;
+                        bucket.refill(now);
+                        if bucket.tokens >= 1.0 {
+                            bucket.tokens -= 1.0;
+                            return;
+                        }
+                        Duration::try_from_secs_f64((1.0 - bucket.tokens) / bucket.rate)
+                            .map_or(bucket.window, |wait| wait.min(bucket.window))
+                    }
+                }
+            };
+
+            time::sleep(wait).await;
+        }
+    }
+
+    /// Number of requests that may currently be sent without waiting.
+    pub fn available(&self) -> u32 {
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+        bucket.refill(Instant::now());
+        bucket.tokens as u32
+    }
+
+    /// Adjust the bucket from the `Ratelimit` and `Ratelimit-Policy` headers
+    /// of a response.
+    pub fn observe(&self, headers: &HeaderMap) {
+        let policy = header_params(headers, "ratelimit-policy");
+        let limit = header_params(headers, "ratelimit");
+        if policy.is_none() && limit.is_none() {
+            return;
+        }
+
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        bucket.refill(now);
+
+        // `q` requests per `w` seconds. A quota below one request would
+        // never fill the bucket with a whole token.
+        if let Some(params) = policy
+            && let (Some(quota), Some(window)) = (param(&params, "q"), param(&params, "w"))
+            && quota >= 1.0
+            && window > 0.0
+            && (quota / window).is_finite()
+            && let Ok(duration) = Duration::try_from_secs_f64(window)
+        {
+            bucket.capacity = quota;
+            bucket.rate = quota / window;
+            bucket.window = duration;
+            bucket.tokens = bucket.tokens.min(quota);
+        }
+
+        // `r` requests remaining, reset in `t` seconds
+        if let Some(params) = limit
+            && let Some(remaining) = param(&params, "r")
+        {
+            bucket.tokens = bucket.tokens.min(remaining);
+            if remaining < 1.0 {
+                let reset = param(&params, "t").unwrap_or(1.0);
+                // Too far in the future to be meant
+                if let Ok(reset) = Duration::try_from_secs_f64(reset)
+                    && let Some(until) = now.checked_add(reset)
+                {
+                    bucket.blocked_until = Some(until);
+                }
+            }
+        }
+    }
+}
+
+/// Parameters of the first item of a structured rate limit header, e.g.
+/// `"default";r=50;t=30`.
+fn header_params(headers: &HeaderMap, name: &str) -> Option<Vec<(String, String)>> {
+    let value = headers.get(name)?.to_str().ok()?;
+    let item = value.split(',').next()?;
+
+    Some(
+        item.split(';')
+            .skip(1)
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| (key.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect(),
+    )
+}
+
+/// A parameter's value; malformed, negative and non-finite values are
+/// ignored.
+fn param(params: &[(String, String)], key: &str) -> Option<f64> {
+    params
+        .iter()
+        .find(|(k, _)| k == key)
+        .and_then(|(_, value)| value.parse().ok())
+        .filter(|value: &f64| value.is_finite() && *value >= 0.0)
+}
@@ -0,0 +1,203 @@
+//! Retrying of rate-limited and transiently failing requests.
+//!
+//! Every request of a generated operation is sent again, according to the
+//! client's [`RetryPolicy`] (see
+//! [`ClientBuilder::retry_policy`](crate::ClientBuilder::retry_policy)),
+//! when it fails with HTTP 429, a 5xx status, a connection error or one of
+//! Cloudflare's transient error codes. `Retry-After` is honoured; otherwise
+//! the delay grows exponentially with full jitter. Callers see the last
+//! response only:
+//!
+//! ```ignore
+//! let client = Client::builder()
+//!     .from_env()?
+//!     .retry_policy(RetryPolicy::default().max_retries(5))
+//!     .build()?;
+//!
+//! let zone = client.zones_get().zone_id(zone_id).send().await.into_result()?;
+//! ```
+
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Method, StatusCode};
+
+/// Cloudflare error codes that indicate a temporary failure.
+pub const TRANSIENT_CODES: &[i64] = &[971, 10013, 10001, 1200];
+
+/// When and how often to retry a failed request.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    retry_non_idempotent: bool,
+    transient_codes: Vec<i64>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+            retry_non_idempotent: false,
+            transient_codes: TRANSIENT_CODES.to_vec(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// Maximum number of retries after the first attempt.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Delay before the first retry; doubled for each further retry.
+    pub fn base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Upper bound for any single delay, including `Retry-After`.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Also retry `POST` and `PATCH` requests.
+    ///
+    /// Off by default, as a request that failed with a 5xx status may still
+    /// have been applied.
+    pub fn retry_non_idempotent(mut self, retry: bool) -> Self {
+        self.retry_non_idempotent = retry;
+        self
+    }
+
+    /// Cloudflare error codes that are retried when a 4xx response carries
+    /// one; 429 and 5xx responses are retried anyway.
+    pub fn transient_codes(mut self, codes: impl IntoIterator<Item = i64>) -> Self {
+        self.transient_codes = codes.into_iter().collect();
+        self
+    }
+
+    /// Whether requests with `method` are retried at all.
+    pub(crate) fn applies_to(&self, method: &Method) -> bool {
+        self.max_retries > 0 && (self.retry_non_idempotent || is_idempotent(method))
+    }
+
+    /// How long to wait before retrying a request that got `result` on its
+    /// `attempt`th retry (0 for the first attempt), or `None` to give up.
+    ///
+    /// `result` is handed back: its body may have been read to look for
+    /// transient error codes.
+    pub(crate) async fn delay(
+        &self,
+        attempt: u32,
+        result: Result<reqwest::Response, reqwest::Error>,
+    ) -> (Result<reqwest::Response, reqwest::Error>, Option<Duration>) {
+        if attempt >= self.max_retries {
+            return (result, None);
+        }
+
+        let response = match result {
+            Ok(response) => response,
+            Err(error) if error.is_timeout() || error.is_connect() => {
+                let delay = self.backoff(attempt);
+                return (Err(error), Some(delay));
+            }
+            Err(error) => return (Err(error), None),
+        };
+
+        let status = response.status();
+        let (response, transient) = if status == StatusCode::TOO_MANY_REQUESTS
+            || status.is_server_error()
+        {
+            (response, true)
+        } else if status.is_client_error() && !self.transient_codes.is_empty() {
+            match error_codes(response).await {
+                Ok((response, codes)) => {
+                    let transient = codes.iter().any(|code| self.transient_codes.contains(code));
+                    (response, transient)
+                }
+                Err(error) => return (Err(error), None),
+            }
+        } else {
+            (response, false)
+        };
+        if !transient {
+            return (Ok(response), None);
+        }
+
+        let delay = retry_after(response.headers())
+            .unwrap_or_else(|| self.backoff(attempt))
+            .min(self.max_delay);
+        (Ok(response), Some(delay))
+    }
+
+    /// Exponential backoff with full jitter.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        ceiling.mul_f64(fastrand::f64())
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE | Method::TRACE
+    )
+}
+
+/// Parse a `Retry-After` header given in seconds.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?;
+    value.trim().parse::<u64>().ok().map(Duration::from_secs)
+}
+
+/// Read the Cloudflare error codes from the body of `response`, returning
+/// an equal response to read it again.
+#[cfg(not(target_arch = "wasm32"))]
+async fn error_codes(
+    response: reqwest::Response,
+) -> Result<(reqwest::Response, Vec<i64>), reqwest::Error> {
+    use reqwest::ResponseBuilderExt;
+
+    let mut builder = http::Response::builder()
+        .status(response.status())
+        .version(response.version())
+        .url(response.url().clone());
+    if let Some(headers) = builder.headers_mut() {
+        *headers = response.headers().clone();
+    }
+    let body = response.bytes().await?;
+
+    let codes = serde_json::from_slice::<crate::Envelope<serde_json::Value>>(&body)
+        .map(|envelope| envelope.errors.iter().map(|e| e.code).collect())
+        .unwrap_or_default();
+    let response = builder
+        .body(body)
+        .expect("the status, version and headers of a response are valid");
+    Ok((reqwest::Response::from(response), codes))
+}
+
+/// A `reqwest::Response` cannot be rebuilt on `wasm32`, so its body is left
+/// alone and only the status decides.
+#[cfg(target_arch = "wasm32")]
+async fn error_codes(
+    response: reqwest::Response,
+) -> Result<(reqwest::Response, Vec<i64>), reqwest::Error> {
+    Ok((response, Vec::new()))
+}
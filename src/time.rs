@@ -0,0 +1,17 @@
+//! Clock and timers for retry delays and rate limiting.
+//!
+//! Native targets use tokio's, so tests can pause time. On `wasm32` there is
+//! no tokio runtime and `std::time::Instant` panics, so the browser (or
+//! Workers) clock and `setTimeout` are used instead.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use tokio::time::{Instant, sleep};
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) use web_time::Instant;
+
+/// Wait for `duration`.
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn sleep(duration: std::time::Duration) {
+    gloo_timers::future::sleep(duration).await
+}
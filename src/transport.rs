@@ -0,0 +1,50 @@
+//! Pluggable HTTP backends.
+//!
+//! The generated operations build a `reqwest::Request` and hand it to the
+//! client's [`HttpBackend`] (see [`ClientBuilder::backend`](crate::ClientBuilder::backend)),
+//! so they do not depend on how requests are sent. Without a backend,
+//! requests are sent by the client's `reqwest::Client`: over hyper on native
+//! targets, and with the `fetch` API on `wasm32`, which inside Cloudflare
+//! Workers is the Workers runtime's `fetch`.
+//!
+//! Backends exchange reqwest's request and response types, which the
+//! generated code and its errors are written against. On `wasm32` only
+//! reqwest can construct a `reqwest::Response`, so there is no backend on
+//! `worker::Fetch`.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+/// The future returned by [`HttpBackend::execute`].
+///
+/// `Send` on native targets; on `wasm32` it may hold JavaScript values, which
+/// are not.
+#[cfg(not(target_arch = "wasm32"))]
+pub type BackendFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<reqwest::Response, reqwest::Error>> + Send + 'a>>;
+
+/// The future returned by [`HttpBackend::execute`].
+///
+/// `Send` on native targets; on `wasm32` it may hold JavaScript values, which
+/// are not.
+#[cfg(target_arch = "wasm32")]
+pub type BackendFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<reqwest::Response, reqwest::Error>> + 'a>>;
+
+/// Sends the requests of a [`Client`](crate::Client).
+///
+/// Requests reach the backend after the client's hooks ran, with the
+/// credentials and `User-Agent` set. On native targets a backend that does
+/// not use reqwest can build its response with
+/// `reqwest::Response::from(http::Response<_>)`.
+pub trait HttpBackend: fmt::Debug + Send + Sync {
+    /// Send `request` and wait for the response head.
+    fn execute(&self, request: reqwest::Request) -> BackendFuture<'_>;
+}
+
+impl HttpBackend for reqwest::Client {
+    fn execute(&self, request: reqwest::Request) -> BackendFuture<'_> {
+        Box::pin(reqwest::Client::execute(self, request))
+    }
+}
@@ -0,0 +1,95 @@
+//! The blocking client against the mock server.
+
+// Closures return the generated client's results, which carry progenitor's error type
+#![allow(clippy::result_large_err)]
+
+use std::time::Duration;
+
+use cloudflare_api::blocking::Paginator;
+use cloudflare_api::mock::{MockResponse, MockServer};
+use cloudflare_api::{PaginationStyle, ResponseExt, RetryPolicy};
+use serde_json::{Value, json};
+
+/// Run a mock server on a thread of its own, the way a synchronous program
+/// talks to a remote API.
+fn mock_server() -> MockServer {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            sender.send(MockServer::start().await.unwrap()).unwrap();
+            std::future::pending::<()>().await
+        })
+    });
+    receiver.recv().unwrap()
+}
+
+#[test]
+fn operations_block_until_the_response() {
+    let mock = mock_server();
+    let client = mock.client_builder().build_blocking().unwrap();
+
+    let zones: Vec<Value> = client.zones_list().send().into_result().unwrap();
+
+    assert!(!zones.is_empty());
+    mock.assert_requested("GET", "/zones", 1);
+}
+
+#[test]
+fn rate_limited_requests_are_retried() {
+    let mock = mock_server();
+    mock.fail_next(2, MockResponse::rate_limited(0));
+    let client = mock
+        .client_builder()
+        .retry_policy(RetryPolicy::default().base_delay(Duration::from_millis(1)))
+        .build_blocking()
+        .unwrap();
+
+    let zones: Result<Vec<Value>, _> = client.zones_list().send().into_result();
+
+    assert!(zones.is_ok());
+    mock.assert_requested("GET", "/zones", 3);
+}
+
+#[test]
+fn paginators_iterate_every_page() {
+    let mock = mock_server();
+    mock.on("GET", "/zones", |request| {
+        let page: u64 = request.query_param("page").unwrap().parse().unwrap();
+        let zones = match page {
+            1..=3 => json!([{ "id": format!("zone-{}", page) }]),
+            _ => json!([]),
+        };
+        MockResponse::json(
+            200,
+            json!({
+                "success": true,
+                "errors": [],
+                "messages": [],
+                "result": zones,
+                "result_info": { "page": page, "per_page": 1, "total_pages": 3 },
+            }),
+        )
+    });
+    let client = mock.client_builder().build_blocking().unwrap();
+
+    let zones: Vec<Value> = Paginator::new(PaginationStyle::Page, |page| {
+        client
+            .zones_list()
+            .page(page.page.unwrap_or(1) as f64)
+            .per_page(page.per_page as f64)
+            .send()
+    })
+    .per_page(1)
+    .collect_all()
+    .unwrap();
+
+    let ids: Vec<&str> = zones
+        .iter()
+        .map(|zone| zone["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, ["zone-1", "zone-2", "zone-3"]);
+}
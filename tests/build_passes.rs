@@ -0,0 +1,319 @@
+//! The build script's schema passes on small specs.
+
+// Only part of each build module is exercised here
+#![allow(dead_code)]
+
+#[path = "../build/diagnostics.rs"]
+mod diagnostics;
+#[path = "../build/enums.rs"]
+mod enums;
+#[path = "../build/filter.rs"]
+mod filter;
+#[path = "../build/hoist.rs"]
+mod hoist;
+#[path = "../build/modules.rs"]
+mod modules;
+#[path = "../build/simplify.rs"]
+mod simplify;
+#[path = "../build/unions.rs"]
+mod unions;
+#[path = "../build/visit.rs"]
+mod visit;
+
+use diagnostics::Diagnostics;
+use serde_json::{Value, json};
+use simplify::{Simplifier, simplify_schema};
+
+/// A spec with one `GET` operation per `(path, tag)`.
+fn spec_with_operations(operations: &[(&str, &str)]) -> Value {
+    let mut paths = serde_json::Map::new();
+    for (path, tag) in operations {
+        paths.insert(
+            path.to_string(),
+            json!({ "get": { "tags": [tag], "responses": {} } }),
+        );
+    }
+    json!({ "openapi": "3.0.3", "paths": paths })
+}
+
+/// Simplify every schema of `spec`, returning the diagnostics summary.
+fn simplify(spec: &mut Value) -> String {
+    let mut diagnostics = Diagnostics::default();
+    let mut simplifier = Simplifier::new(spec, &mut diagnostics);
+    visit::for_each_schema(spec, &mut |schema, location| {
+        simplify_schema(schema, location, &mut simplifier)
+    });
+    diagnostics.summary()
+}
+
+fn kept_paths(spec: &Value) -> Vec<&str> {
+    spec["paths"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect()
+}
+
+#[test]
+fn workers_do_not_select_workers_kv() {
+    let operations = [
+        ("/accounts/{account_id}/workers/scripts", "Worker Script"),
+        ("/zones/{zone_id}/workers/routes", "Worker Routes"),
+        (
+            "/accounts/{account_id}/storage/kv/namespaces",
+            "Workers KV Namespace",
+        ),
+        (
+            "/accounts/{account_id}/kv-analytics",
+            "Workers KV Request Analytics",
+        ),
+    ];
+
+    let mut spec = spec_with_operations(&operations);
+    filter::filter_product_areas(&mut spec, &["workers"], &mut Diagnostics::default()).unwrap();
+    assert_eq!(
+        kept_paths(&spec),
+        [
+            "/accounts/{account_id}/workers/scripts",
+            "/zones/{zone_id}/workers/routes",
+        ]
+    );
+
+    let mut spec = spec_with_operations(&operations);
+    filter::filter_product_areas(&mut spec, &["kv"], &mut Diagnostics::default()).unwrap();
+    assert_eq!(
+        kept_paths(&spec),
+        [
+            "/accounts/{account_id}/kv-analytics",
+            "/accounts/{account_id}/storage/kv/namespaces",
+        ]
+    );
+}
+
+#[test]
+fn zones_select_whole_tag_names() {
+    let operations = [
+        ("/zones", "Zone"),
+        ("/zones/{zone_id}/settings/ipv6", "Zone Settings"),
+        ("/zones/{zone_id}/firewall/lockdowns", "Zone Lockdown"),
+        ("/zones/{zone_id}/rulesets", "Zone Rulesets"),
+    ];
+
+    let mut spec = spec_with_operations(&operations);
+    filter::filter_product_areas(&mut spec, &["zones"], &mut Diagnostics::default()).unwrap();
+    assert_eq!(
+        kept_paths(&spec),
+        ["/zones", "/zones/{zone_id}/settings/ipv6"]
+    );
+}
+
+#[test]
+fn unions_keep_the_properties_next_to_them() {
+    let mut spec = json!({
+        "components": { "schemas": { "dns_record": {
+            "type": "object",
+            "required": ["name"],
+            "properties": {
+                "name": { "type": "string" },
+                "ttl": { "type": "integer" },
+            },
+            "oneOf": [
+                {
+                    "required": ["type", "content"],
+                    "properties": {
+                        "type": { "enum": ["A"] },
+                        "content": { "type": "string" },
+                    },
+                },
+                {
+                    "required": ["type", "priority"],
+                    "properties": {
+                        "type": { "enum": ["MX"] },
+                        "priority": { "type": "integer" },
+                    },
+                },
+            ],
+        } } },
+    });
+
+    assert_eq!(simplify(&mut spec), "");
+    let record = &spec["components"]["schemas"]["dns_record"];
+    assert!(record.get("properties").is_none());
+
+    let members = record["oneOf"].as_array().unwrap();
+    assert_eq!(members.len(), 2);
+    for (member, specific) in members.iter().zip(["content", "priority"]) {
+        assert_eq!(member["type"], "object");
+        for property in ["name", "ttl", "type", specific] {
+            assert!(member["properties"].get(property).is_some(), "{}", property);
+        }
+        assert_eq!(member["required"], json!(["name", "type", specific]));
+    }
+}
+
+#[test]
+fn unions_of_required_lists_keep_the_schema_next_to_them() {
+    let mut spec = json!({
+        "components": { "schemas": { "rule": {
+            "type": "object",
+            "properties": {
+                "expression": { "type": "string" },
+                "ref": { "type": "string" },
+            },
+            "anyOf": [{ "required": ["expression"] }, { "required": ["ref"] }],
+        } } },
+    });
+
+    assert_eq!(simplify(&mut spec), "1 collapsed-union");
+    assert_eq!(
+        spec["components"]["schemas"]["rule"],
+        json!({
+            "type": "object",
+            "properties": {
+                "expression": { "type": "string" },
+                "ref": { "type": "string" },
+            },
+        })
+    );
+}
+
+/// A base schema and a schema extending it with `allOf`.
+fn inheriting(extension: Value) -> Value {
+    json!({
+        "components": { "schemas": {
+            "base": {
+                "type": "object",
+                "required": ["id"],
+                "properties": {
+                    "id": { "type": "string" },
+                    "name": { "type": "string" },
+                },
+            },
+            "derived": { "allOf": [{ "$ref": "#/components/schemas/base" }, extension] },
+        } },
+    })
+}
+
+#[test]
+fn allof_members_are_resolved() {
+    let mut spec = inheriting(json!({
+        "required": ["status"],
+        "properties": { "status": { "type": "string" } },
+    }));
+
+    assert_eq!(simplify(&mut spec), "");
+    assert_eq!(
+        spec["components"]["schemas"]["derived"],
+        json!({
+            "type": "object",
+            "required": ["id", "status"],
+            "properties": {
+                "id": { "type": "string" },
+                "name": { "type": "string" },
+                "status": { "type": "string" },
+            },
+        })
+    );
+}
+
+#[test]
+fn unions_in_allof_members_keep_the_inherited_properties() {
+    let mut spec = inheriting(json!({
+        "oneOf": [
+            { "properties": { "value": { "type": "string" } } },
+            { "properties": { "value": { "type": "integer" } } },
+        ],
+    }));
+
+    assert_eq!(simplify(&mut spec), "");
+    let members = spec["components"]["schemas"]["derived"]["oneOf"]
+        .as_array()
+        .unwrap();
+    assert_eq!(members.len(), 2);
+    for (member, ty) in members.iter().zip(["string", "integer"]) {
+        assert_eq!(member["required"], json!(["id"]));
+        assert!(member["properties"].get("name").is_some());
+        assert_eq!(member["properties"]["value"]["type"], ty);
+    }
+}
+
+#[test]
+fn second_unions_in_allof_are_reported() {
+    let mut spec = inheriting(json!({
+        "allOf": [
+            { "oneOf": [{ "type": "string" }, { "type": "integer" }] },
+            { "oneOf": [{ "type": "boolean" }, { "type": "number" }] },
+        ],
+    }));
+
+    assert_eq!(simplify(&mut spec), "1 collapsed-union");
+}
+
+fn component_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+#[test]
+fn union_members_are_hoisted_after_their_title_or_tag() {
+    let mut spec = json!({
+        "components": { "schemas": {
+            "dns_record": { "oneOf": [
+                {
+                    "title": "A Record",
+                    "type": "object",
+                    "properties": {
+                        "type": { "type": "string", "enum": ["A"] },
+                        "content": { "type": "string" },
+                    },
+                },
+                {
+                    "type": "object",
+                    "properties": {
+                        "type": { "type": "string", "enum": ["MX"] },
+                        "priority": { "type": "integer" },
+                    },
+                },
+                { "type": "object", "properties": { "comment": { "type": "string" } } },
+            ] },
+            "rule": {
+                "discriminator": { "propertyName": "kind" },
+                "anyOf": [
+                    {
+                        "type": "object",
+                        "properties": {
+                            "category": { "type": "string", "enum": ["firewall"] },
+                            "kind": { "type": "string", "enum": ["block"] },
+                        },
+                    },
+                    {
+                        "type": "object",
+                        "properties": {
+                            "category": { "type": "string", "enum": ["firewall"] },
+                            "kind": { "type": "string", "enum": ["log"] },
+                        },
+                    },
+                ],
+            },
+        } },
+    });
+
+    hoist::hoist_inline_schemas(&mut spec);
+    let schemas = &spec["components"]["schemas"];
+    assert_eq!(
+        schemas["dns_record"]["oneOf"],
+        json!([
+            component_ref("dns_record_a_record"),
+            component_ref("dns_record_mx"),
+            component_ref("dns_record_variant_2"),
+        ])
+    );
+    assert_eq!(
+        schemas["dns_record_mx"]["properties"]["priority"],
+        json!({ "type": "integer" })
+    );
+    assert_eq!(
+        schemas["rule"]["anyOf"],
+        json!([component_ref("rule_block"), component_ref("rule_log")])
+    );
+}
@@ -0,0 +1,178 @@
+//! The generated client against recorded cassettes.
+
+use cloudflare_api::cassette::{ACCOUNT_ID_PLACEHOLDER, Cassette, Matcher, REDACTED};
+use cloudflare_api::fake::{FAKE_ACCOUNT_ID, FakeCloudflare};
+use cloudflare_api::mock::{MOCK_API_TOKEN, MockResponse, MockServer};
+use cloudflare_api::prelude::*;
+use cloudflare_api::{Client, ResponseExt};
+use serde_json::{Value, json};
+
+const ZONES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/zones.json");
+
+async fn get(client: &Client, path: &str) -> reqwest::Response {
+    client
+        .client()
+        .get(format!("{}{}", client.baseurl(), path))
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn replays_recorded_responses() {
+    let cassette = Cassette::replay(ZONES).await.unwrap();
+    let client = cassette.client();
+
+    let zones: Vec<Value> = client.zones_list().send().await.into_result().unwrap();
+    assert_eq!(zones[0]["name"], "example.com");
+
+    // Any account ID matches the scrubbed recording
+    let namespaces: Value = get(&client, "/accounts/3f2c9d1e/storage/kv/namespaces")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(namespaces["result"][0]["title"], "settings");
+
+    cassette.finish().unwrap();
+}
+
+#[tokio::test]
+async fn unmatched_requests_fail_loudly() {
+    let cassette = Cassette::replay(ZONES).await.unwrap();
+    let client = cassette.client();
+
+    let response = get(&client, "/zones?name=example.org").await;
+    assert_eq!(response.status(), 500);
+
+    let error = cassette.finish().unwrap_err();
+    assert!(error.to_string().contains("GET /zones?name=example.org"));
+}
+
+#[tokio::test]
+async fn matchers_can_ignore_the_query() {
+    let cassette = Cassette::builder(ZONES)
+        .matcher(Matcher::new().ignore_query())
+        .replay()
+        .await
+        .unwrap();
+    let client = cassette.client();
+
+    let response = get(&client, "/zones?name=example.com").await;
+    assert_eq!(response.status(), 200);
+
+    cassette.finish().unwrap();
+}
+
+#[tokio::test]
+async fn recordings_are_scrubbed_and_replayable() {
+    let fake = FakeCloudflare::start().await.unwrap();
+    fake.add_namespace("settings");
+    let path = std::env::temp_dir().join(format!(
+        "cloudflare-api-cassette-{}.json",
+        std::process::id()
+    ));
+
+    let cassette = Cassette::builder(&path)
+        .upstream(fake.url())
+        .record()
+        .await
+        .unwrap();
+    let client = cassette
+        .client_builder()
+        .api_token(MOCK_API_TOKEN)
+        .build()
+        .unwrap();
+    let namespaces_path = format!("/accounts/{}/storage/kv/namespaces", FAKE_ACCOUNT_ID);
+    let recorded: Value = get(&client, &namespaces_path).await.json().await.unwrap();
+    assert_eq!(recorded["result"][0]["title"], "settings");
+    cassette.finish().unwrap();
+
+    let file = std::fs::read_to_string(&path).unwrap();
+    assert!(!file.contains(MOCK_API_TOKEN));
+    assert!(!file.contains(FAKE_ACCOUNT_ID));
+    assert!(file.contains(REDACTED));
+    assert!(file.contains(ACCOUNT_ID_PLACEHOLDER));
+
+    let cassette = Cassette::replay(&path).await.unwrap();
+    let replayed: Value = get(&cassette.client(), &namespaces_path)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(replayed, recorded);
+    cassette.finish().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn secrets_in_responses_are_scrubbed_from_the_whole_recording() {
+    const ACCOUNT_ID: &str = "6f5c1e0a9b8d4c3e2f1a0b9c8d7e6f5a";
+    const TOKEN: &str = "8M7wS6hCpXVc-DoRnPPY_UCWPgy8aea4Wy6kCe5T";
+    const VALUE: &[u8] = &[0x00, 0x9f, 0x92, 0x96, 0xff];
+
+    let mock = MockServer::start().await.unwrap();
+    mock.on("GET", "/zones", |_| {
+        MockResponse::success(json!([{
+            "id": "023e105f4ecef8ad9ca31a8372d0c353",
+            "name": "example.com",
+            "account": { "id": ACCOUNT_ID, "name": "Example" },
+        }]))
+    });
+    mock.on("POST", "/user/tokens", |_| {
+        MockResponse::success(json!({ "id": "ed17574386854bf78a67040be0a770b0", "value": TOKEN }))
+    });
+    mock.on(
+        "GET",
+        "/accounts/{account_id}/storage/kv/namespaces/{namespace_id}/values/{key_name}",
+        |_| {
+            MockResponse::raw(200, VALUE.to_vec())
+                .header("content-type", "application/octet-stream")
+        },
+    );
+    let path = std::env::temp_dir().join(format!(
+        "cloudflare-api-cassette-secrets-{}.json",
+        std::process::id()
+    ));
+
+    let cassette = Cassette::builder(&path)
+        .upstream(mock.url())
+        .record()
+        .await
+        .unwrap();
+    let client = cassette
+        .client_builder()
+        .api_token(MOCK_API_TOKEN)
+        .build()
+        .unwrap();
+    get(&client, "/zones").await;
+    client
+        .client()
+        .post(format!("{}/user/tokens", client.baseurl()))
+        .json(&json!({ "name": "readonly" }))
+        .send()
+        .await
+        .unwrap();
+    // The account ID learned from the zones response
+    get(&client, &format!("/zones?account.id={}", ACCOUNT_ID)).await;
+    let value_path = "/accounts/ACCOUNT_ID/storage/kv/namespaces/0f2ac74b/values/blob";
+    let recorded = get(&client, value_path).await.bytes().await.unwrap();
+    assert_eq!(&recorded[..], VALUE);
+    cassette.finish().unwrap();
+
+    let file = std::fs::read_to_string(&path).unwrap();
+    assert!(!file.contains(ACCOUNT_ID));
+    assert!(!file.contains(TOKEN));
+    assert!(file.contains("account.id=ACCOUNT_ID"));
+    assert!(file.contains("\"encoding\": \"base64\""));
+
+    let cassette = Cassette::replay(&path).await.unwrap();
+    let replayed = get(&cassette.client(), value_path)
+        .await
+        .bytes()
+        .await
+        .unwrap();
+    assert_eq!(&replayed[..], VALUE);
+    cassette.finish().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
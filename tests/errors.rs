@@ -0,0 +1,156 @@
+//! Mapping of Cloudflare error codes to `CloudflareError` variants.
+
+use cloudflare_api::{CloudflareError, Envelope, Error, ResponseValue};
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+use serde_json::{Value, json};
+
+/// The error of a failed envelope reporting `errors`.
+fn failure_with(errors: Value) -> CloudflareError {
+    let envelope: Envelope<Value> = serde_json::from_value(json!({
+        "success": false,
+        "errors": errors,
+        "messages": [],
+        "result": null,
+    }))
+    .unwrap();
+
+    envelope.into_result().unwrap_err()
+}
+
+fn failure(code: i64) -> CloudflareError {
+    failure_with(json!([{ "code": code, "message": "failed" }]))
+}
+
+/// The error of a generated operation that got a `status` response with
+/// `body`.
+fn error_response(status: StatusCode, body: Value) -> CloudflareError {
+    let error: Error<Value> =
+        Error::ErrorResponse(ResponseValue::new(body, status, HeaderMap::new()));
+    CloudflareError::from(error)
+}
+
+#[test]
+fn authentication_failures_are_recognised() {
+    for code in [10000, 9103, 9106, 9109] {
+        assert!(
+            matches!(failure(code), CloudflareError::Authentication(_)),
+            "code {}",
+            code
+        );
+    }
+}
+
+#[test]
+fn rate_limits_are_recognised() {
+    for code in [10013, 971] {
+        let error = failure(code);
+        assert!(error.is_rate_limited(), "code {}", code);
+    }
+}
+
+#[test]
+fn existing_records_are_recognised() {
+    for code in [81053, 81057, 81058] {
+        assert!(
+            matches!(failure(code), CloudflareError::RecordAlreadyExists(_)),
+            "code {}",
+            code
+        );
+    }
+}
+
+#[test]
+fn missing_zones_are_recognised() {
+    assert!(matches!(failure(1001), CloudflareError::ZoneNotFound(_)));
+}
+
+#[test]
+fn validation_errors_are_recognised() {
+    for code in [1004, 6003, 6007, 9207] {
+        assert!(
+            matches!(failure(code), CloudflareError::Validation(_)),
+            "code {}",
+            code
+        );
+    }
+
+    // Any code, as long as the error points at the offending field
+    let error = failure_with(json!([{
+        "code": 12345,
+        "message": "invalid TTL",
+        "source": { "pointer": "/ttl" },
+    }]));
+    assert!(matches!(error, CloudflareError::Validation(_)));
+    assert_eq!(
+        error.to_string(),
+        "Invalid request: invalid TTL (12345) at /ttl"
+    );
+}
+
+#[test]
+fn codes_in_error_chains_are_recognised() {
+    let error = failure_with(json!([{
+        "code": 12345,
+        "message": "failed",
+        "error_chain": [{ "code": 1001, "message": "zone not found" }],
+    }]));
+
+    assert!(matches!(error, CloudflareError::ZoneNotFound(_)));
+    assert_eq!(error.code(), Some(12345));
+}
+
+#[test]
+fn unknown_codes_fall_back_to_other() {
+    let error = failure(12345);
+
+    let CloudflareError::Other { code, error } = &error else {
+        panic!("expected Other, got {:?}", error);
+    };
+    assert_eq!(*code, 12345);
+    assert_eq!(error.errors[0].message, "failed");
+
+    // A failure without any reported error
+    assert!(matches!(
+        failure_with(json!([])),
+        CloudflareError::Other { code: 0, .. }
+    ));
+}
+
+#[test]
+fn error_responses_carry_their_status() {
+    let error = error_response(
+        StatusCode::CONFLICT,
+        json!({
+            "success": false,
+            "errors": [{ "code": 81057, "message": "record already exists" }],
+            "messages": [],
+            "result": null,
+        }),
+    );
+
+    assert!(matches!(error, CloudflareError::RecordAlreadyExists(_)));
+    assert_eq!(error.status(), Some(StatusCode::CONFLICT));
+}
+
+#[test]
+fn statuses_without_errors_are_recognised() {
+    let empty = json!({ "success": false, "errors": [], "messages": [], "result": null });
+
+    let error = error_response(StatusCode::TOO_MANY_REQUESTS, empty.clone());
+    assert!(error.is_rate_limited());
+    assert_eq!(error.code(), None);
+    assert_eq!(
+        error.to_string(),
+        "Rate limited: HTTP 429 Too Many Requests"
+    );
+
+    let error = error_response(StatusCode::UNAUTHORIZED, Value::Null);
+    assert!(matches!(error, CloudflareError::Authentication(_)));
+    assert_eq!(error.status(), Some(StatusCode::UNAUTHORIZED));
+
+    // Other statuses stay client errors
+    let error = error_response(StatusCode::INTERNAL_SERVER_ERROR, empty);
+    assert!(matches!(error, CloudflareError::Client(_)));
+    assert_eq!(error.status(), Some(StatusCode::INTERNAL_SERVER_ERROR));
+}
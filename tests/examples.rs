@@ -0,0 +1,137 @@
+//! Spec examples round-tripped through the generated types.
+//!
+//! Every example in a request or response body of the patched spec is
+//! deserialized into the body's generated type and serialized back. A failure
+//! means the type cannot represent the example, e.g. because schema patching
+//! dropped a required field or mis-typed an enum; fix the schema (or the
+//! example) with an overlay, see `overlays/README.md`, or, if it cannot be
+//! fixed, list its location in `schema/known_bad_examples.json` with the
+//! reason.
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+type RoundTrip = fn(&Value) -> Result<Value, String>;
+
+include!(concat!(env!("OUT_DIR"), "/examples.rs"));
+
+/// Deserialize `example` into `T` and serialize it back.
+fn round_trip<T: DeserializeOwned + Serialize>(example: &Value) -> Result<Value, String> {
+    let value: T = serde_json::from_value(example.clone())
+        .map_err(|e| format!("does not deserialize: {}", e))?;
+    serde_json::to_value(value).map_err(|e| format!("does not serialize: {}", e))
+}
+
+/// Where `actual` lost or changed a value of `expected`. Nulls the type omits
+/// and fields it adds (e.g. defaults) are not mismatches.
+fn mismatches(path: &str, expected: &Value, actual: &Value, found: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Null, _) => {}
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (name, value) in expected {
+                let field = format!("{}.{}", path, name);
+                match actual.get(name) {
+                    Some(actual) => mismatches(&field, value, actual, found),
+                    None if value.is_null() => {}
+                    None => found.push(format!("{} was dropped", field)),
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) if expected.len() == actual.len() => {
+            for (i, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                mismatches(&format!("{}[{}]", path, i), expected, actual, found);
+            }
+        }
+        (Value::Number(expected), Value::Number(actual))
+            if expected.as_f64() == actual.as_f64() => {}
+        (Value::String(expected), Value::String(actual))
+            if normalize_timestamp(expected) == normalize_timestamp(actual) => {}
+        (expected, actual) if expected == actual => {}
+        (expected, actual) => {
+            found.push(format!("{}: expected {}, got {}", path, expected, actual))
+        }
+    }
+}
+
+/// Spell an RFC 3339 timestamp the way chrono may re-serialize it: a `Z` for
+/// UTC and no trailing zeros in the fraction. Other strings are unchanged.
+fn normalize_timestamp(value: &str) -> String {
+    let is_timestamp = value.len() > 19
+        && value.as_bytes()[10] == b'T'
+        && value[..10].bytes().all(|b| b.is_ascii_digit() || b == b'-');
+    if !is_timestamp {
+        return value.to_string();
+    }
+
+    let value = value
+        .strip_suffix("+00:00")
+        .map_or(value.to_string(), |v| format!("{}Z", v));
+    match value.strip_suffix('Z').and_then(|v| v.split_once('.')) {
+        Some((seconds, fraction)) => {
+            let fraction = fraction.trim_end_matches('0');
+            if fraction.is_empty() {
+                format!("{}Z", seconds)
+            } else {
+                format!("{}.{}Z", seconds, fraction)
+            }
+        }
+        None => value,
+    }
+}
+
+/// The mismatches of every example, `location [schema]: mismatch`, split
+/// into those of known-bad examples and the others, and the known-bad
+/// examples that round-trip.
+fn check_examples() -> (Vec<String>, Vec<String>, Vec<String>) {
+    let (mut failures, mut known, mut fixed) = (Vec::new(), Vec::new(), Vec::new());
+
+    for (location, schema, example, round_trip) in EXAMPLES {
+        let example: Value = serde_json::from_str(example).unwrap();
+        let mut found = Vec::new();
+        match round_trip(&example) {
+            Ok(actual) => mismatches("$", &example, &actual, &mut found),
+            Err(error) => found.push(error),
+        }
+
+        let is_known_bad = KNOWN_BAD.iter().any(|(known, _)| known == location);
+        if is_known_bad && found.is_empty() {
+            fixed.push(format!("{} [{}]", location, schema));
+        }
+        let found = found
+            .into_iter()
+            .map(|mismatch| format!("{} [{}]: {}", location, schema, mismatch));
+        if is_known_bad {
+            known.extend(found);
+        } else {
+            failures.extend(found);
+        }
+    }
+
+    (failures, known, fixed)
+}
+
+#[test]
+fn spec_examples_round_trip() {
+    let (failures, known, _) = check_examples();
+
+    assert!(
+        failures.is_empty(),
+        "{} mismatches in {} spec examples ({} more in known-bad examples):\n{}",
+        failures.len(),
+        EXAMPLES.len(),
+        known.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn known_bad_examples_still_fail() {
+    let (_, _, fixed) = check_examples();
+
+    assert!(
+        fixed.is_empty(),
+        "these examples round-trip now, remove them from schema/known_bad_examples.json:\n{}",
+        fixed.join("\n")
+    );
+}
@@ -0,0 +1,226 @@
+//! The generated client against the stateful fake.
+
+use cloudflare_api::dns::types::DnsRecordsDnsRecordPost;
+use cloudflare_api::fake::{FAKE_ACCOUNT_ID, FakeCloudflare};
+use cloudflare_api::prelude::*;
+use cloudflare_api::zones::types::ZonesCreateRequest;
+use cloudflare_api::{CloudflareError, ResponseExt};
+use futures::TryStreamExt;
+use serde_json::{Value, json};
+
+/// A generated request body from its JSON form.
+fn body<T: serde::de::DeserializeOwned>(value: Value) -> T {
+    serde_json::from_value(value).unwrap()
+}
+
+/// Create a DNS record in `zone_id`.
+async fn create_record(
+    client: &Client,
+    zone_id: &str,
+    record: Value,
+) -> Result<Value, CloudflareError> {
+    client
+        .dns_records_create()
+        .zone_id(zone_id)
+        .body(body::<DnsRecordsDnsRecordPost>(record))
+        .send()
+        .await
+        .into_result()
+}
+
+#[tokio::test]
+async fn zones_are_remembered() {
+    let fake = FakeCloudflare::start().await.unwrap();
+    let client = fake.client();
+
+    let created: Value = client
+        .zones_create()
+        .body(body::<ZonesCreateRequest>(
+            json!({ "name": "example.com", "account": { "id": FAKE_ACCOUNT_ID } }),
+        ))
+        .send()
+        .await
+        .into_result()
+        .unwrap();
+    let zone_id = created["id"].as_str().unwrap();
+
+    let zones: Vec<Value> = client.zones_list().send().await.into_result().unwrap();
+    assert_eq!(zones.len(), 1);
+    assert_eq!(zones[0]["name"], "example.com");
+
+    let zone: Value = client
+        .zones_get()
+        .zone_id(zone_id)
+        .send()
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(zone["id"], zone_id);
+}
+
+#[tokio::test]
+async fn zone_errors_use_cloudflare_codes() {
+    let fake = FakeCloudflare::start().await.unwrap();
+    fake.add_zone("example.com");
+    let client = fake.client();
+
+    let duplicate = client
+        .zones_create()
+        .body(body::<ZonesCreateRequest>(json!({ "name": "example.com" })))
+        .send()
+        .await
+        .into_result::<Value>()
+        .unwrap_err();
+    assert_eq!(duplicate.code(), Some(1061));
+
+    let missing = client
+        .zones_get()
+        .zone_id("0123456789abcdef0123456789abcdef")
+        .send()
+        .await
+        .into_result::<Value>()
+        .unwrap_err();
+    assert!(matches!(missing, CloudflareError::ZoneNotFound(_)));
+}
+
+#[tokio::test]
+async fn dns_records_can_be_created_listed_and_deleted() {
+    let fake = FakeCloudflare::start().await.unwrap();
+    let zone_id = fake.add_zone("example.com");
+    let client = fake.client();
+
+    let record = create_record(
+        &client,
+        &zone_id,
+        json!({ "type": "A", "name": "www", "content": "192.0.2.1", "ttl": 3600 }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(record["name"], "www.example.com");
+    let record_id = record["id"].as_str().unwrap();
+
+    let records: Vec<Value> = client
+        .dns_records_list()
+        .zone_id(&zone_id)
+        .send()
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(records.len(), 1);
+
+    client
+        .dns_records_delete()
+        .zone_id(&zone_id)
+        .dns_record_id(record_id)
+        .send()
+        .await
+        .into_result::<Value>()
+        .unwrap();
+    assert!(fake.dns_records(&zone_id).is_empty());
+}
+
+#[tokio::test]
+async fn dns_records_are_validated() {
+    let fake = FakeCloudflare::start().await.unwrap();
+    let zone_id = fake.add_zone("example.com");
+    let client = fake.client();
+
+    let invalid = create_record(
+        &client,
+        &zone_id,
+        json!({ "type": "A", "name": "www", "content": "not-an-ip" }),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(invalid, CloudflareError::Validation(_)));
+    assert!(invalid.api_error().unwrap().codes().contains(&9005));
+
+    let www = json!({ "type": "A", "name": "www", "content": "192.0.2.1" });
+    create_record(&client, &zone_id, www.clone()).await.unwrap();
+    let duplicate = create_record(&client, &zone_id, www).await.unwrap_err();
+    assert!(matches!(duplicate, CloudflareError::RecordAlreadyExists(_)));
+
+    let cname = create_record(
+        &client,
+        &zone_id,
+        json!({ "type": "CNAME", "name": "www", "content": "example.net" }),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(cname.code(), Some(81053));
+}
+
+#[tokio::test]
+async fn kv_values_can_be_written_and_read() {
+    let fake = FakeCloudflare::start().await.unwrap();
+    let namespace_id = fake.add_namespace("settings");
+    let client = fake.client();
+
+    client
+        .storage_kv_namespaces_values_update()
+        .account_id(FAKE_ACCOUNT_ID)
+        .namespace_id(&namespace_id)
+        .key_name("feature/flag")
+        .body("on")
+        .send()
+        .await
+        .into_result::<Value>()
+        .unwrap();
+    assert_eq!(
+        fake.kv_value(&namespace_id, "feature/flag").as_deref(),
+        Some(&b"on"[..])
+    );
+
+    let read: Vec<u8> = client
+        .storage_kv_namespaces_values_get()
+        .account_id(FAKE_ACCOUNT_ID)
+        .namespace_id(&namespace_id)
+        .key_name("feature/flag")
+        .send()
+        .await
+        .unwrap()
+        .into_inner()
+        .into_inner()
+        .map_ok(|chunk| chunk.to_vec())
+        .try_concat()
+        .await
+        .unwrap();
+    assert_eq!(read, b"on");
+
+    client
+        .storage_kv_namespaces_values_delete()
+        .account_id(FAKE_ACCOUNT_ID)
+        .namespace_id(&namespace_id)
+        .key_name("feature/flag")
+        .send()
+        .await
+        .into_result::<Value>()
+        .unwrap();
+    assert!(fake.kv_value(&namespace_id, "feature/flag").is_none());
+
+    let Err(missing) = client
+        .storage_kv_namespaces_values_get()
+        .account_id(FAKE_ACCOUNT_ID)
+        .namespace_id(&namespace_id)
+        .key_name("feature/flag")
+        .send()
+        .await
+    else {
+        panic!("the value was deleted");
+    };
+    assert_eq!(missing.status(), Some(reqwest::StatusCode::NOT_FOUND));
+}
+
+#[tokio::test]
+async fn requests_without_credentials_are_rejected() {
+    let fake = FakeCloudflare::start().await.unwrap();
+
+    let response: Value = reqwest::get(format!("{}/zones", fake.url()))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(response["errors"][0]["code"], 10000);
+}
@@ -0,0 +1,262 @@
+//! The generated client against the mock server.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use cloudflare_api::mock::{MOCK_API_TOKEN, MockResponse, MockServer};
+use cloudflare_api::prelude::*;
+use cloudflare_api::{
+    BackendFuture, Error, HttpBackend, PaginationStyle, Paginator, ResponseExt, RetryPolicy,
+    SchemaDrift,
+};
+use serde_json::{Value, json};
+
+const ZONE_ID: &str = "023e105f4ecef8ad9ca31a8372d0c353";
+
+#[tokio::test]
+async fn serves_canned_responses_from_the_spec() {
+    let mock = MockServer::start().await.unwrap();
+    let client = mock.client();
+
+    let zones: Vec<Value> = client.zones_list().send().await.into_result().unwrap();
+
+    assert!(!zones.is_empty());
+    mock.assert_requested("GET", "/zones", 1);
+}
+
+#[tokio::test]
+async fn custom_handlers_see_the_request() {
+    let mock = MockServer::start().await.unwrap();
+    mock.on("GET", "/zones/{zone_id}", |request| {
+        MockResponse::success(json!({ "id": request.params["zone_id"], "name": "example.com" }))
+    });
+    let client = mock.client();
+
+    let zone: Value = client
+        .zones_get()
+        .zone_id(ZONE_ID)
+        .send()
+        .await
+        .into_result()
+        .unwrap();
+
+    assert_eq!(zone["id"], ZONE_ID);
+    let requests = mock.requests_to("GET", "/zones/{zone_id}");
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].params["zone_id"], ZONE_ID);
+    assert_eq!(
+        requests[0].headers["authorization"],
+        format!("Bearer {}", MOCK_API_TOKEN)
+    );
+}
+
+#[tokio::test]
+async fn rate_limited_requests_are_retried() {
+    let mock = MockServer::start().await.unwrap();
+    mock.fail_next(2, MockResponse::rate_limited(0));
+    let client = mock
+        .client_builder()
+        .retry_policy(RetryPolicy::default().base_delay(Duration::from_millis(1)))
+        .build()
+        .unwrap();
+
+    let zones: Result<Vec<Value>, _> = client.zones_list().send().await.into_result();
+
+    assert!(zones.is_ok());
+    mock.assert_requested("GET", "/zones", 3);
+}
+
+#[tokio::test]
+async fn transient_error_codes_are_retried() {
+    let mock = MockServer::start().await.unwrap();
+    mock.fail_next(1, MockResponse::error(400, 971, "Please wait"));
+    let client = mock
+        .client_builder()
+        .retry_policy(RetryPolicy::default().base_delay(Duration::from_millis(1)))
+        .build()
+        .unwrap();
+
+    let zones: Vec<Value> = client.zones_list().send().await.into_result().unwrap();
+
+    assert!(!zones.is_empty());
+    mock.assert_requested("GET", "/zones", 2);
+}
+
+#[tokio::test]
+async fn injected_errors_reach_the_caller() {
+    let mock = MockServer::start().await.unwrap();
+    mock.fail_next(1, MockResponse::rate_limited(30));
+    let client = mock.client();
+
+    let error = client
+        .zones_list()
+        .send()
+        .await
+        .into_result::<Vec<Value>>()
+        .unwrap_err();
+
+    assert!(error.is_rate_limited());
+}
+
+/// Counts the requests it sends.
+#[derive(Debug)]
+struct CountingBackend {
+    http: reqwest::Client,
+    sent: Arc<AtomicUsize>,
+}
+
+impl HttpBackend for CountingBackend {
+    fn execute(&self, request: reqwest::Request) -> BackendFuture<'_> {
+        self.sent.fetch_add(1, Ordering::SeqCst);
+        Box::pin(self.http.execute(request))
+    }
+}
+
+#[tokio::test]
+async fn requests_go_through_a_custom_backend() {
+    let mock = MockServer::start().await.unwrap();
+    let sent = Arc::new(AtomicUsize::new(0));
+    let client = mock
+        .client_builder()
+        .backend(CountingBackend {
+            http: reqwest::Client::new(),
+            sent: sent.clone(),
+        })
+        .build()
+        .unwrap();
+
+    let zones: Vec<Value> = client.zones_list().send().await.into_result().unwrap();
+
+    assert!(!zones.is_empty());
+    assert_eq!(sent.load(Ordering::SeqCst), 1);
+    // The credentials the client's own `reqwest::Client` would have sent
+    let requests = mock.requests_to("GET", "/zones");
+    assert_eq!(
+        requests[0].headers["authorization"],
+        format!("Bearer {}", MOCK_API_TOKEN)
+    );
+}
+
+#[tokio::test]
+async fn schema_drift_is_dropped_when_logged() {
+    let mock = MockServer::start().await.unwrap();
+    // `result_info.page` is a number in the spec
+    let drifted = json!({
+        "success": true,
+        "errors": [],
+        "messages": [],
+        "result": [],
+        "result_info": { "page": "first" },
+    });
+    mock.on("GET", "/zones", move |_| {
+        MockResponse::json(200, drifted.clone())
+    });
+
+    let strict = mock.client();
+    let zones = strict.zones_list().send().await;
+    assert!(matches!(zones, Err(Error::InvalidResponsePayload(..))));
+
+    let lenient = mock
+        .client_builder()
+        .schema_drift(SchemaDrift::Log)
+        .build()
+        .unwrap();
+    let zones: Vec<Value> = lenient.zones_list().send().await.into_result().unwrap();
+    assert!(zones.is_empty());
+}
+
+#[tokio::test]
+async fn unknown_routes_are_not_found() {
+    let mock = MockServer::start().await.unwrap();
+
+    let response = reqwest::get(format!("{}/no/such/route", mock.url()))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 404);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["success"], false);
+    assert_eq!(body["errors"][0]["code"], 7003);
+}
+
+/// Serve five zones from `GET /zones`, at most two per page, with the
+/// `result_info` fields `info` picks from the full set.
+fn capped_zones(mock: &MockServer, info: fn(Value) -> Value) {
+    mock.on("GET", "/zones", move |request| {
+        let page: usize = request.query_param("page").unwrap().parse().unwrap();
+        let zones: Vec<Value> = (1..=5)
+            .map(|i| json!({ "id": format!("zone-{}", i) }))
+            .skip((page - 1) * 2)
+            .take(2)
+            .collect();
+        let result_info = json!({
+            "page": page,
+            "per_page": 2,
+            "count": zones.len(),
+            "total_count": 5,
+            "total_pages": 3,
+        });
+        MockResponse::json(
+            200,
+            json!({
+                "success": true,
+                "errors": [],
+                "messages": [],
+                "result": zones,
+                "result_info": info(result_info),
+            }),
+        )
+    });
+}
+
+async fn zone_ids(client: &Client) -> Vec<String> {
+    let zones: Vec<Value> = Paginator::new(PaginationStyle::Page, |page| {
+        client
+            .zones_list()
+            .page(page.page.unwrap_or(1) as f64)
+            .per_page(page.per_page as f64)
+            .send()
+    })
+    .per_page(5)
+    .collect_all()
+    .await
+    .unwrap();
+
+    zones
+        .iter()
+        .map(|zone| zone["id"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn pages_capped_by_the_server_are_all_fetched() {
+    let all = ["zone-1", "zone-2", "zone-3", "zone-4", "zone-5"];
+
+    // Trusting `total_pages`
+    let mock = MockServer::start().await.unwrap();
+    capped_zones(&mock, |info| info);
+    assert_eq!(zone_ids(&mock.client()).await, all);
+    mock.assert_requested("GET", "/zones", 3);
+
+    // Pages counted from `total_count`
+    let mock = MockServer::start().await.unwrap();
+    capped_zones(
+        &mock,
+        |info| json!({ "page": info["page"], "total_count": 5 }),
+    );
+    assert_eq!(zone_ids(&mock.client()).await, all);
+    mock.assert_requested("GET", "/zones", 3);
+
+    // No totals: the short last page, as the server reports two per page
+    let mock = MockServer::start().await.unwrap();
+    capped_zones(&mock, |info| json!({ "page": info["page"], "per_page": 2 }));
+    assert_eq!(zone_ids(&mock.client()).await, all);
+    mock.assert_requested("GET", "/zones", 3);
+
+    // Nothing to go by: until an empty page
+    let mock = MockServer::start().await.unwrap();
+    capped_zones(&mock, |info| json!({ "page": info["page"] }));
+    assert_eq!(zone_ids(&mock.client()).await, all);
+    mock.assert_requested("GET", "/zones", 4);
+}
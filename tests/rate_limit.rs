@@ -0,0 +1,65 @@
+//! Adjusting the rate limiter from response headers.
+
+use std::time::Duration;
+
+use cloudflare_api::RateLimiter;
+use reqwest::header::HeaderMap;
+
+fn headers(limit: &str, policy: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("ratelimit", limit.parse().unwrap());
+    headers.insert("ratelimit-policy", policy.parse().unwrap());
+    headers
+}
+
+#[test]
+fn malformed_headers_are_ignored() {
+    for (limit, policy) in [
+        ("\"default\";r=NaN;t=30", "\"default\";q=inf;w=300"),
+        ("\"default\";r=-1;t=30", "\"default\";q=10;w=NaN"),
+        ("\"default\";r=inf", "\"default\";q=1e308;w=1e-308"),
+        ("\"default\";r=ten", "\"default\";q=-10;w=-1"),
+    ] {
+        let limiter = RateLimiter::new(10, Duration::from_secs(60));
+
+        limiter.observe(&headers(limit, policy));
+
+        assert_eq!(limiter.available(), 10, "{}, {}", limit, policy);
+    }
+}
+
+#[test]
+fn malformed_reset_times_do_not_panic() {
+    for reset in ["NaN", "-inf", "-5", "1e300"] {
+        let limiter = RateLimiter::new(10, Duration::from_secs(60));
+
+        limiter.observe(&headers(
+            &format!("\"default\";r=0;t={}", reset),
+            "\"default\";q=10;w=60",
+        ));
+
+        assert_eq!(limiter.available(), 0, "t={}", reset);
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn tiny_policy_rates_do_not_overflow() {
+    let limiter = RateLimiter::new(1, Duration::from_secs(60));
+
+    // One request per 1e300 seconds is ignored, as the window is not a duration
+    limiter.observe(&headers("\"default\";r=1;t=1", "\"default\";q=1;w=1e300"));
+
+    limiter.acquire().await;
+    let start = tokio::time::Instant::now();
+    limiter.acquire().await;
+    assert!(start.elapsed() <= Duration::from_secs(60));
+}
+
+#[test]
+fn fractional_quotas_are_ignored() {
+    let limiter = RateLimiter::new(10, Duration::from_secs(60));
+
+    limiter.observe(&headers("\"default\";r=10;t=1", "\"default\";q=0.5;w=1"));
+
+    assert_eq!(limiter.available(), 10);
+}